@@ -0,0 +1,61 @@
+// Decompression of gzip, bzip2, xz and zstd input for `-z`, recognised by the magic bytes each
+// format begins with rather than by file name, so that it works on stdin too.
+
+use std::io;
+use std::io::{BufRead, BufReader, Cursor, Read};
+
+use bzip2::bufread::MultiBzDecoder;
+use flate2::bufread::MultiGzDecoder;
+use xz2::bufread::XzDecoder;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// The length of the longest magic number.
+const MAGIC_LEN: usize = 6;
+
+// Wrap `reader` in a decoder for the format its contents are compressed with, or return it as it
+// is if they aren't recognised. Concatenated streams, as produced by appending to a compressed log,
+// are decompressed in full.
+pub fn decompress(mut reader: Box<dyn BufRead>) -> io::Result<Box<dyn BufRead>> {
+    // A single read may return fewer bytes than the magic number, e.g. from a pipe, so read until
+    // there are enough or the input ends, then put them back in front of the rest.
+    let mut magic = Vec::with_capacity(MAGIC_LEN);
+    reader
+        .by_ref()
+        .take(MAGIC_LEN as u64)
+        .read_to_end(&mut magic)?;
+    let format = Format::detect(&magic);
+    let reader = Cursor::new(magic).chain(reader);
+
+    Ok(match format {
+        Some(Format::Gzip) => Box::new(BufReader::new(MultiGzDecoder::new(reader))),
+        Some(Format::Bzip2) => Box::new(BufReader::new(MultiBzDecoder::new(reader))),
+        Some(Format::Xz) => Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader))),
+        Some(Format::Zstd) => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        None => Box::new(reader),
+    })
+}
+
+enum Format {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Format {
+    fn detect(magic: &[u8]) -> Option<Format> {
+        [
+            (GZIP_MAGIC, Format::Gzip),
+            (BZIP2_MAGIC, Format::Bzip2),
+            (XZ_MAGIC, Format::Xz),
+            (ZSTD_MAGIC, Format::Zstd),
+        ]
+        .into_iter()
+        .find(|(format_magic, _)| magic.starts_with(format_magic))
+        .map(|(_, format)| format)
+    }
+}
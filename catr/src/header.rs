@@ -0,0 +1,82 @@
+// Headers written before each file with -H or --header-template, so that the boundaries between
+// concatenated files stay visible in the output.
+
+use std::io;
+use std::io::{BufRead, Write};
+
+// The placeholders a header template may contain.
+const PATH: &str = "{path}";
+const LINES: &str = "{lines}";
+const BYTES: &str = "{bytes}";
+
+// The size of a file's contents, as shown in its header.
+pub struct Counts {
+    lines: usize,
+    bytes: usize,
+}
+
+impl Counts {
+    // Count what's left of `reader`, copying it to `out` on the way. Lines are counted as wc does,
+    // by their newlines, so a last line without one isn't counted.
+    pub fn of(mut reader: impl BufRead, out: &mut impl Write) -> io::Result<Counts> {
+        let mut counts = Counts { lines: 0, bytes: 0 };
+        loop {
+            let buf = reader.fill_buf()?;
+            if buf.is_empty() {
+                return Ok(counts);
+            }
+            counts.lines += buf.iter().filter(|&&byte| byte == b'\n').count();
+            counts.bytes += buf.len();
+            out.write_all(buf)?;
+            let len = buf.len();
+            reader.consume(len);
+        }
+    }
+}
+
+// Whether `template` shows counts, which can only be known once the whole file has been read.
+pub fn needs_counts(template: &str) -> bool {
+    template.contains(LINES) || template.contains(BYTES)
+}
+
+// Write the header for the file at `path`: `template` with its placeholders filled in, if there is
+// one, or otherwise `==> path <==` as headr prints it, preceded by a blank line unless it's the
+// first, and with the counts, if given, before the closing arrows.
+pub fn write(
+    out: &mut impl Write,
+    template: Option<&str>,
+    path: &str,
+    counts: Option<&Counts>,
+    is_first: bool,
+) -> io::Result<()> {
+    let path = match path {
+        "-" => "standard input",
+        _ => path,
+    };
+
+    if let Some(template) = template {
+        let mut header = template.replace(PATH, path);
+        if let Some(counts) = counts {
+            header = header
+                .replace(LINES, &counts.lines.to_string())
+                .replace(BYTES, &counts.bytes.to_string());
+        }
+        return writeln!(out, "{}", header);
+    }
+
+    if !is_first {
+        writeln!(out)?;
+    }
+    match counts {
+        Some(counts) => writeln!(
+            out,
+            "==> {} ({} {}, {} {}) <==",
+            path,
+            counts.lines,
+            if counts.lines == 1 { "line" } else { "lines" },
+            counts.bytes,
+            if counts.bytes == 1 { "byte" } else { "bytes" },
+        ),
+        None => writeln!(out, "==> {} <==", path),
+    }
+}
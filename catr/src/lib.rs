@@ -1,36 +1,180 @@
-use std::fs::File;
-use std::io;
-use std::io::{BufRead, BufReader};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, StdinLock, Take, Write};
+use std::{env, fs, io, process};
 
-use cat::Cat;
+use cat::{Cat, Progress};
 
 pub use cat::Args;
 
-fn open(file_path: &str) -> cat::Result<Box<dyn BufRead>> {
+mod decompress;
+mod header;
+mod range;
+
+// An input file, kept as its concrete type so that copying it unchanged can use the zero-copy
+// system calls, such as `copy_file_range` and `splice`, that `io::copy` picks for files and the
+// standard streams. Each is limited to the end of the range given with --bytes, if any.
+enum Input {
+    Stdin(Take<StdinLock<'static>>),
+    File(Take<File>),
+}
+
+impl Input {
+    fn copy_to(self, out: &mut impl Write) -> io::Result<u64> {
+        match self {
+            Input::Stdin(mut stdin) => io::copy(&mut stdin, out),
+            Input::File(mut file) => io::copy(&mut file, out),
+        }
+    }
+
+    fn into_reader(self) -> Box<dyn BufRead> {
+        match self {
+            Input::Stdin(stdin) => Box::new(stdin),
+            Input::File(file) => Box::new(BufReader::new(file)),
+        }
+    }
+
+    // Skip to the start of `range`, seeking straight there in a regular file, and stop at its end.
+    fn select_bytes(self, range: range::Range) -> io::Result<Input> {
+        match self {
+            Input::Stdin(stdin) => {
+                let mut stdin = stdin.into_inner();
+                range::discard(&mut stdin, range.skip())?;
+                Ok(Input::Stdin(stdin.take(range.count())))
+            }
+            Input::File(file) => {
+                let mut file = file.into_inner();
+                if file.metadata()?.is_file() {
+                    file.seek(SeekFrom::Start(range.skip()))?;
+                } else {
+                    range::discard(&mut file, range.skip())?;
+                }
+                Ok(Input::File(file.take(range.count())))
+            }
+        }
+    }
+}
+
+fn open(file_path: &str) -> cat::Result<Input> {
     match file_path {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(file_path)?))),
+        "-" => Ok(Input::Stdin(io::stdin().lock().take(u64::MAX))),
+        _ => Ok(Input::File(File::open(file_path)?.take(u64::MAX))),
     }
 }
 
 pub fn run(args: Args) -> cat::Result<()> {
+    // Lock stdout once rather than on every write, and buffer output across lines.
+    let mut out = BufWriter::new(io::stdout().lock());
+    // As with GNU cat, numbering, squeezing and the last line carry on from one file to the next.
+    let mut progress = Progress::new(&args);
+    let mut is_first = true;
     for path in &args {
-        match open(path) {
-            Err(err) => eprintln!("Failed to open {}: {}", path, err),
-            Ok(reader) => Cat::run(&args, reader)?,
+        let input = match open(path) {
+            Err(err) => {
+                eprintln!("Failed to open {}: {}", path, err);
+                continue;
+            }
+            Ok(input) => input,
+        };
+        // With -z the range is of the decompressed bytes, so it's selected by `reader` instead.
+        let input = match args.bytes() {
+            Some(range) if !args.decompress() => input.select_bytes(range)?,
+            _ => input,
+        };
+
+        if args.header_counts() {
+            // The header comes before the contents, so whatever is selected of them has to be read
+            // through to count it before it's printed.
+            let (counts, reader) = count(&args, input)?;
+            header::write(
+                &mut out,
+                args.header_template(),
+                path,
+                Some(&counts),
+                is_first,
+            )?;
+            progress.start_line();
+            Cat::run(&args, reader, &mut out, &mut progress)?;
+        } else {
+            if args.headers() {
+                header::write(&mut out, args.header_template(), path, None, is_first)?;
+                progress.start_line();
+            }
+            if !args.transforms() && !args.decompress() {
+                // Nothing to transform, so pass the bytes straight through.
+                input.copy_to(&mut out)?;
+            } else {
+                Cat::run(&args, reader(&args, input)?, &mut out, &mut progress)?;
+            }
         }
+        is_first = false;
     }
+    out.flush()?;
     Ok(())
 }
 
+// Count the contents `reader` selects from `input` and return a reader that selects them again.
+// A regular file is read twice, going back to where the first reading started, while other input
+// can only be read once, so it's copied to a temporary file as it's counted.
+fn count(args: &Args, input: Input) -> io::Result<(header::Counts, Box<dyn BufRead>)> {
+    if let Input::File(file) = &input {
+        if file.get_ref().metadata()?.is_file() {
+            let limit = file.limit();
+            // A clone shares the file's position, so note where reading starts before it moves.
+            let mut again = file.get_ref().try_clone()?;
+            let start = again.stream_position()?;
+            let counts = header::Counts::of(reader(args, input)?, &mut io::sink())?;
+            again.seek(SeekFrom::Start(start))?;
+            return Ok((counts, reader(args, Input::File(again.take(limit)))?));
+        }
+    }
+
+    let mut copy = temp_file()?;
+    let counts = header::Counts::of(reader(args, input)?, &mut copy)?;
+    copy.rewind()?;
+    Ok((counts, Box::new(BufReader::new(copy))))
+}
+
+// Create a file for temporary use and remove it at once, so nothing is left behind when it's
+// closed, however catr exits.
+fn temp_file() -> io::Result<File> {
+    let path = env::temp_dir().join(format!("catr-{}", process::id()));
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+    Ok(file)
+}
+
+// Read `input`, decompressing it with -z, and select the range given with --lines, if any.
+fn reader(args: &Args, input: Input) -> io::Result<Box<dyn BufRead>> {
+    let mut reader = input.into_reader();
+    if args.decompress() {
+        reader = decompress::decompress(reader)?;
+        if let Some(range) = args.bytes() {
+            range::discard(&mut reader, range.skip())?;
+            reader = Box::new(reader.take(range.count()));
+        }
+    }
+    if let Some(range) = args.lines() {
+        reader = Box::new(range::select_lines(reader, range)?);
+    }
+    Ok(reader)
+}
+
 mod cat {
     use std::error::Error;
-    use std::io::BufRead;
+    use std::io::{BufRead, Write};
     use std::result;
     use std::slice::Iter;
 
     use clap::Parser;
 
+    use crate::header;
+    use crate::range;
+    use crate::range::Range;
+
     pub type Result<T> = result::Result<T, Box<dyn Error>>;
 
     #[derive(Debug, Parser)]
@@ -41,20 +185,168 @@ mod cat {
         #[arg(value_name = "FILE", default_value = "-")]
         files: Vec<String>,
 
-        /// Number the output lines, starting at 1
-        #[arg(short('n'), long("number"), conflicts_with("number_nonblank_lines"))]
+        /// Number the output lines, continuing across files
+        #[arg(
+            short('n'),
+            long("number"),
+            group("numbering"),
+            conflicts_with("number_nonblank_lines")
+        )]
         number_lines: bool,
 
-        /// Number the non-blank output lines, starting at 1
-        #[arg(short('b'), long("number-nonblank"))]
+        /// Number the non-blank output lines, continuing across files
+        #[arg(short('b'), long("number-nonblank"), group("numbering"))]
         number_nonblank_lines: bool,
 
+        /// The number of the first numbered line
+        #[arg(
+            long("starting-line-number"),
+            value_name = "NUMBER",
+            default_value_t = 1,
+            allow_negative_numbers = true,
+            requires("numbering")
+        )]
+        starting_line_number: i64,
+
+        /// The amount to add to the line number for each numbered line
+        #[arg(
+            short('i'),
+            long("line-increment"),
+            value_name = "NUMBER",
+            default_value_t = 1,
+            allow_negative_numbers = true,
+            requires("numbering")
+        )]
+        line_increment: i64,
+
+        /// The width of line numbers, which are right-aligned
+        #[arg(
+            short('w'),
+            long("number-width"),
+            value_name = "WIDTH",
+            default_value_t = 6,
+            requires("numbering")
+        )]
+        number_width: usize,
+
+        /// The text between a line number and its line, a tab by default
+        #[arg(
+            long("number-separator"),
+            value_name = "STRING",
+            default_value = "\t",
+            hide_default_value = true,
+            requires("numbering")
+        )]
+        number_separator: String,
+
         /// Squeeze multiple adjacent empty lines, causing the output to be single-spaced
         #[arg(short('s'), long("squeeze"))]
         squeeze: bool,
+
+        /// Display control characters as ^X and bytes above 127 as M- followed by the character for
+        /// their low 7 bits, except for tabs and newlines
+        #[arg(short('v'), long("show-nonprinting"))]
+        show_nonprinting: bool,
+
+        /// Display $ at the end of each line
+        #[arg(short('E'), long("show-ends"))]
+        show_ends: bool,
+
+        /// Display tabs as ^I
+        #[arg(short('T'), long("show-tabs"))]
+        show_tabs: bool,
+
+        /// Equivalent to -vET
+        #[arg(short('A'), long("show-all"))]
+        show_all: bool,
+
+        /// Decompress input compressed with gzip, bzip2, xz or zstd, recognised by its contents,
+        /// and pass other input through unchanged
+        #[arg(short('z'), long("decompress"))]
+        decompress: bool,
+
+        /// Print a header of the form ==> FILE <== before each file, with a blank line between
+        /// files
+        #[arg(short('H'), long("headers"))]
+        headers: bool,
+
+        /// Print TEMPLATE as the header before each file, replacing {path}, {lines} and {bytes}
+        /// with the file's path and the number of lines and bytes printed from it
+        #[arg(long("header-template"), value_name = "TEMPLATE")]
+        header_template: Option<String>,
+
+        /// Include the number of lines and bytes printed from each file, before any formatting, in
+        /// its header
+        #[arg(
+            long("header-counts"),
+            requires("headers"),
+            conflicts_with("header_template")
+        )]
+        header_counts: bool,
+
+        /// Print only lines FIRST to LAST of each file, counting from 1; either may be left out
+        #[arg(long("lines"), value_name = "FIRST:LAST", value_parser = range::parse)]
+        lines: Option<Range>,
+
+        /// Print only bytes FIRST to LAST of each file, counting from 1; either may be left out
+        #[arg(
+            long("bytes"),
+            value_name = "FIRST:LAST",
+            value_parser = range::parse,
+            conflicts_with("lines")
+        )]
+        bytes: Option<Range>,
     }
 
     impl Args {
+        // Whether any option changes the output, rather than copying the input as it is.
+        pub(crate) fn transforms(&self) -> bool {
+            self.number_lines
+                || self.number_nonblank_lines
+                || self.squeeze
+                || self.show_nonprinting()
+                || self.show_ends()
+                || self.show_tabs()
+                || self.lines.is_some()
+        }
+
+        pub(crate) fn decompress(&self) -> bool {
+            self.decompress
+        }
+
+        pub(crate) fn lines(&self) -> Option<Range> {
+            self.lines
+        }
+
+        pub(crate) fn bytes(&self) -> Option<Range> {
+            self.bytes
+        }
+
+        pub(crate) fn headers(&self) -> bool {
+            self.headers || self.header_template.is_some()
+        }
+
+        pub(crate) fn header_template(&self) -> Option<&str> {
+            self.header_template.as_deref()
+        }
+
+        // Whether headers show counts, which means reading each file through before writing it.
+        pub(crate) fn header_counts(&self) -> bool {
+            self.header_counts || self.header_template().is_some_and(header::needs_counts)
+        }
+
+        fn show_nonprinting(&self) -> bool {
+            self.show_nonprinting || self.show_all
+        }
+
+        fn show_ends(&self) -> bool {
+            self.show_ends || self.show_all
+        }
+
+        fn show_tabs(&self) -> bool {
+            self.show_tabs || self.show_all
+        }
+
         // Return an immutable iterator over the list of files passed as arguments.
         fn iter(&self) -> Iter<'_, String> {
             self.files.iter()
@@ -75,62 +367,176 @@ mod cat {
         }
     }
 
-    pub struct Cat<'a> {
+    // Where the output has got to, which is shared by every file.
+    pub struct Progress {
+        // The number of the next numbered line, or None if it would overflow.
+        line_number: Option<i64>,
+        // Whether the next line written starts a line of output, which it doesn't if the last file
+        // ended without a newline.
+        at_line_start: bool,
+        prev_line_empty: bool,
+    }
+
+    impl Progress {
+        pub fn new(args: &Args) -> Progress {
+            Progress {
+                line_number: Some(args.starting_line_number),
+                at_line_start: true,
+                prev_line_empty: false,
+            }
+        }
+
+        // Note that a header has ended the current line of output.
+        pub fn start_line(&mut self) {
+            self.at_line_start = true;
+        }
+    }
+
+    pub struct Cat<'a, W: Write> {
         args: &'a Args,
         reader: Box<dyn BufRead>,
-        line_counter: u32,
-        cur_line: String,
-        prev_line_empty: bool,
+        out: &'a mut W,
+        progress: &'a mut Progress,
+        // Lines are raw bytes, since files needn't be UTF-8, and -v must show bytes that aren't.
+        cur_line: Vec<u8>,
     }
 
-    impl Cat<'_> {
-        pub fn run(args: &Args, reader: Box<dyn BufRead>) -> Result<()> {
-            Cat::new(args, reader).cat()
+    impl<'a, W: Write> Cat<'a, W> {
+        pub fn run(
+            args: &Args,
+            reader: Box<dyn BufRead>,
+            out: &mut W,
+            progress: &mut Progress,
+        ) -> Result<()> {
+            Cat::new(args, reader, out, progress).cat()
         }
 
-        fn new(args: &Args, reader: Box<dyn BufRead>) -> Cat<'_> {
+        fn new(
+            args: &'a Args,
+            reader: Box<dyn BufRead>,
+            out: &'a mut W,
+            progress: &'a mut Progress,
+        ) -> Cat<'a, W> {
             Cat {
                 args,
                 reader,
-                line_counter: 1,
-                prev_line_empty: false,
-                cur_line: String::new(),
+                out,
+                progress,
+                cur_line: Vec::new(),
             }
         }
 
         fn cat(&mut self) -> Result<()> {
             // `BufRead.lines` omits trailing newlines, which makes it impossible to emulate the behavior of
             // `cat`, since we have no way of determining whether the file ends with a newline.
-            // `BufRead.read_line` preserves newlines.
-            while self.reader.read_line(&mut self.cur_line)? != 0 {
-                self.cat_line();
+            // `BufRead.read_until` preserves newlines.
+            while self.reader.read_until(b'\n', &mut self.cur_line)? != 0 {
+                self.cat_line()?;
                 self.cur_line.clear();
             }
 
             Ok(())
         }
 
-        fn cat_line(&mut self) {
-            let cur_line_empty = self.cur_line.trim().is_empty();
+        fn cat_line(&mut self) -> Result<()> {
+            let mut cur_line_empty = self.cur_line.trim_ascii().is_empty();
 
-            // Squeeze
-            if self.args.squeeze && self.prev_line_empty && cur_line_empty {
-                return;
+            if self.progress.at_line_start {
+                // Squeeze
+                if self.args.squeeze && self.progress.prev_line_empty && cur_line_empty {
+                    return Ok(());
+                }
+
+                // Number
+                self.number_line(cur_line_empty)?;
+            } else {
+                // The rest of the last line of the previous file, which was squeezed or numbered
+                // when it began.
+                cur_line_empty &= self.progress.prev_line_empty;
             }
 
-            // Number
-            self.number_line(cur_line_empty);
+            if self.args.show_nonprinting() || self.args.show_ends() || self.args.show_tabs() {
+                let shown = self.show_line();
+                self.out.write_all(&shown)?;
+            } else {
+                self.out.write_all(&self.cur_line)?;
+            }
 
-            print!("{}", self.cur_line);
+            self.progress.prev_line_empty = cur_line_empty;
+            self.progress.at_line_start = self.cur_line.ends_with(b"\n");
+            Ok(())
+        }
+
+        // Apply -v, -E and -T to the current line.
+        fn show_line(&self) -> Vec<u8> {
+            let (mut body, newline) = match self.cur_line.strip_suffix(b"\n") {
+                Some(body) => (body, true),
+                None => (&self.cur_line[..], false),
+            };
+            // As with GNU cat, -E shows the carriage return of a CRLF line ending even without -v.
+            let crlf = newline && self.args.show_ends() && body.ends_with(b"\r");
+            if crlf {
+                body = &body[..body.len() - 1];
+            }
 
-            self.prev_line_empty = cur_line_empty;
+            let mut shown = Vec::with_capacity(self.cur_line.len() * 2);
+            for &byte in body {
+                if byte == b'\t' {
+                    if self.args.show_tabs() {
+                        shown.extend_from_slice(b"^I");
+                    } else {
+                        shown.push(byte);
+                    }
+                } else if self.args.show_nonprinting() {
+                    show_byte(&mut shown, byte);
+                } else {
+                    shown.push(byte);
+                }
+            }
+
+            if newline {
+                if crlf {
+                    shown.extend_from_slice(b"^M");
+                }
+                if self.args.show_ends() {
+                    shown.push(b'$');
+                }
+                shown.push(b'\n');
+            }
+            shown
         }
 
-        fn number_line(&mut self, cur_line_empty: bool) {
+        fn number_line(&mut self, cur_line_empty: bool) -> Result<()> {
             if self.args.number_lines || (self.args.number_nonblank_lines && !cur_line_empty) {
-                print!("{:6}\t", self.line_counter);
-                self.line_counter += 1;
+                // As with nl, overflow is only an error once there's a line to number with it.
+                let line_number = self.progress.line_number.ok_or("line number overflow")?;
+                write!(
+                    self.out,
+                    "{:>width$}{}",
+                    line_number,
+                    self.args.number_separator,
+                    width = self.args.number_width
+                )?;
+                self.progress.line_number = line_number.checked_add(self.args.line_increment);
             }
+            Ok(())
+        }
+    }
+
+    // Write a byte in the notation of `cat -v`: M- for bytes above 127, followed by ^ and the
+    // corresponding letter for control characters, ^? for DEL, or otherwise the character itself.
+    fn show_byte(shown: &mut Vec<u8>, byte: u8) {
+        let byte = if byte >= 128 {
+            shown.extend_from_slice(b"M-");
+            byte - 128
+        } else {
+            byte
+        };
+
+        match byte {
+            0..=31 => shown.extend_from_slice(&[b'^', byte + 64]),
+            127 => shown.extend_from_slice(b"^?"),
+            _ => shown.push(byte),
         }
     }
 }
@@ -1,7 +1,15 @@
+use std::io;
+
 use clap::Parser;
 
 fn main() {
     if let Err(e) = catr::run(catr::Args::parse()) {
+        // Whatever was reading the output has stopped, as `head` does, so there's no one to tell.
+        if e.downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+        {
+            return;
+        }
         eprintln!("{}", e);
         std::process::exit(1);
     }
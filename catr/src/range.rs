@@ -0,0 +1,114 @@
+// Ranges of lines or bytes selected with --lines and --bytes. As with cut and sed, they count from
+// 1 and include both ends, so 1:10 is the first ten.
+
+use std::io;
+use std::io::{BufRead, Read};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Range {
+    first: u64,
+    last: Option<u64>,
+}
+
+impl Range {
+    // The number of lines or bytes before the range.
+    pub fn skip(&self) -> u64 {
+        self.first - 1
+    }
+
+    // The number of lines or bytes in the range, which is unlimited if it has no end.
+    pub fn count(&self) -> u64 {
+        match self.last {
+            Some(last) => last - self.first + 1,
+            None => u64::MAX,
+        }
+    }
+}
+
+// Parse a range written as FIRST:LAST, where either end may be left out to start at the beginning
+// or go on to the end, or as a single number to select just that line or byte.
+pub fn parse(arg: &str) -> Result<Range, String> {
+    let parse_end = |end: &str, default| match end {
+        "" => Ok(default),
+        _ => match end.parse() {
+            Ok(0) => Err("lines and bytes are counted from 1".to_string()),
+            Ok(n) => Ok(Some(n)),
+            Err(e) => Err(format!("{}: {}", end, e)),
+        },
+    };
+
+    let (first, last) = match arg.split_once(':') {
+        Some((first, last)) => (parse_end(first, Some(1))?, parse_end(last, None)?),
+        None => {
+            let n = parse_end(arg, None)?.ok_or("expected FIRST:LAST")?;
+            (Some(n), Some(n))
+        }
+    };
+    let first = first.unwrap_or(1);
+    match last {
+        Some(last) if last < first => Err(format!("{} comes after {}", first, last)),
+        _ => Ok(Range { first, last }),
+    }
+}
+
+// Read and discard `n` bytes, for input that can't seek.
+pub fn discard(reader: &mut impl Read, n: u64) -> io::Result<()> {
+    io::copy(&mut reader.take(n), &mut io::sink())?;
+    Ok(())
+}
+
+// Skip the lines before `range` in `reader` and stop at the end of its last line.
+pub fn select_lines<R: BufRead>(mut reader: R, range: Range) -> io::Result<TakeLines<R>> {
+    for _ in 0..range.skip() {
+        if reader.skip_until(b'\n')? == 0 {
+            break;
+        }
+    }
+    Ok(TakeLines {
+        inner: reader,
+        remaining: range.count(),
+        line_end: None,
+    })
+}
+
+// A reader that stops after a number of lines, as `Take` does after a number of bytes, so that
+// the rest of the input is never read.
+pub struct TakeLines<R> {
+    inner: R,
+    remaining: u64,
+    // The length of the buffer last returned by `fill_buf` if it ends a line.
+    line_end: Option<usize>,
+}
+
+impl<R: BufRead> Read for TakeLines<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for TakeLines<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.remaining == 0 {
+            return Ok(&[]);
+        }
+        // Return no more than the rest of the current line, so `consume` can tell when it ends.
+        let buf = self.inner.fill_buf()?;
+        let buf = match buf.iter().position(|&byte| byte == b'\n') {
+            Some(newline) => &buf[..=newline],
+            None => buf,
+        };
+        self.line_end = buf.ends_with(b"\n").then_some(buf.len());
+        Ok(buf)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.line_end.take() == Some(amt) {
+            self.remaining -= 1;
+        }
+        self.inner.consume(amt);
+    }
+}
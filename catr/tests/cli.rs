@@ -12,6 +12,9 @@ const FOX: &str = "tests/inputs/fox.txt";
 const SPIDERS: &str = "tests/inputs/spiders.txt";
 const BUSTLE: &str = "tests/inputs/the-bustle.txt";
 const SQUEEZE: &str = "tests/inputs/squeeze.txt";
+const BLANK_ENDS: &str = "tests/inputs/blank-ends.txt";
+const NONPRINTING: &str = "tests/inputs/nonprinting.txt";
+const BINARY: &str = "tests/inputs/binary.bin";
 
 // --------------------------------------------------
 #[test]
@@ -64,6 +67,30 @@ fn run(args: &[&str], expected_file: &str) -> TestResult {
     Ok(())
 }
 
+// --------------------------------------------------
+fn run_binary(args: &[&str], expected_file: &str) -> TestResult {
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdin_binary(input_file: &str, args: &[&str], expected_file: &str) -> TestResult {
+    let input = fs::read(input_file)?;
+    let expected = fs::read(expected_file)?;
+    Command::cargo_bin(PRG)?
+        .args(args)
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
 // --------------------------------------------------
 fn run_stdin(input_file: &str, args: &[&str], expected_file: &str) -> TestResult {
     let input = fs::read_to_string(input_file)?;
@@ -196,8 +223,352 @@ fn all_b() -> TestResult {
     run(&[FOX, SPIDERS, BUSTLE, "-b"], "tests/expected/all.b.out")
 }
 
+// --------------------------------------------------
+#[test]
+fn all_n_format() -> TestResult {
+    run(
+        &[
+            "-n",
+            "--starting-line-number",
+            "-10",
+            "-i",
+            "5",
+            "-w",
+            "3",
+            "--number-separator",
+            ": ",
+            FOX,
+            SPIDERS,
+        ],
+        "tests/expected/all.n.format.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_b_format() -> TestResult {
+    run(
+        &[
+            "-b",
+            "--starting-line-number=100",
+            "--number-width=4",
+            "--number-separator= | ",
+            BUSTLE,
+        ],
+        "tests/expected/the-bustle.txt.b.format.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn line_number_overflow() -> TestResult {
+    let max = i64::MAX.to_string();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--starting-line-number", &max])
+        .write_stdin("a\n")
+        .assert()
+        .success()
+        .stdout(format!("{}\ta\n", max));
+    Command::cargo_bin(PRG)?
+        .args(["-n", "--starting-line-number", &max])
+        .write_stdin("a\nb\n")
+        .assert()
+        .failure()
+        .stdout(format!("{}\ta\n", max))
+        .stderr("line number overflow\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_without_numbering() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["-w", "3", FOX])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--number"));
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn squeeze_s() -> TestResult {
     run(&[SQUEEZE, "-s"], "tests/expected/squeeze.txt.s.out")
 }
+
+// --------------------------------------------------
+#[test]
+fn squeeze_fox_n() -> TestResult {
+    // squeeze.txt has no trailing newline, so its last line runs on into fox.txt.
+    run(&["-n", SQUEEZE, FOX], "tests/expected/squeeze_fox.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn blank_ends_bs() -> TestResult {
+    run(
+        &["-bs", BLANK_ENDS, BLANK_ENDS],
+        "tests/expected/blank-ends.txt.bs.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_v() -> TestResult {
+    run(&["-v", NONPRINTING], "tests/expected/nonprinting.txt.v.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_e() -> TestResult {
+    run(
+        &["--show-ends", NONPRINTING],
+        "tests/expected/nonprinting.txt.E.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_t() -> TestResult {
+    run(&["-T", NONPRINTING], "tests/expected/nonprinting.txt.T.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_a() -> TestResult {
+    run(&["-A", NONPRINTING], "tests/expected/nonprinting.txt.A.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn nonprinting_vet() -> TestResult {
+    run(
+        &["-vET", NONPRINTING],
+        "tests/expected/nonprinting.txt.A.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn binary() -> TestResult {
+    run_binary(&[BINARY], BINARY)
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_stdin() -> TestResult {
+    let input = fs::read(BINARY)?;
+    Command::cargo_bin(PRG)?
+        .args([BINARY, "-"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout([input.clone(), input].concat());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_n() -> TestResult {
+    run_binary(&["-n", BINARY], "tests/expected/binary.bin.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn binary_s() -> TestResult {
+    run_binary(&["-s", BINARY], "tests/expected/binary.bin.s.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn broken_pipe() -> TestResult {
+    use std::io::Read;
+    use std::process::{Command, Stdio};
+
+    // Far more output than a pipe can hold, so catr is still writing when the reader goes away.
+    for flags in [&[][..], &["-n"]] {
+        let mut child = Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(flags)
+            .args(vec![BUSTLE; 5000])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let mut start = [0; 16];
+        child.stdout.take().unwrap().read_exact(&mut start)?;
+
+        let out = child.wait_with_output()?;
+        assert!(out.status.success());
+        assert_eq!(String::from_utf8(out.stderr)?, "");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress() -> TestResult {
+    run(
+        &["-z", "tests/inputs/fox.txt.gz"],
+        "tests/expected/fox.txt.out",
+    )?;
+    run(
+        &["--decompress", "tests/inputs/spiders.txt.bz2"],
+        "tests/expected/spiders.txt.out",
+    )?;
+    run(
+        &["-z", "tests/inputs/the-bustle.txt.xz"],
+        "tests/expected/the-bustle.txt.out",
+    )?;
+    run(
+        &["-z", "tests/inputs/squeeze.txt.zst", FOX],
+        "tests/expected/squeeze_fox.z.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_formatted() -> TestResult {
+    run(
+        &["-zn", "tests/inputs/the-bustle.txt.xz"],
+        "tests/expected/the-bustle.txt.n.out",
+    )?;
+    run_stdin_binary(
+        "tests/inputs/squeeze.txt.zst",
+        &["-zs"],
+        "tests/expected/squeeze.txt.s.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress_concatenated() -> TestResult {
+    run(
+        &["-zb", "tests/inputs/rotated.log.gz"],
+        "tests/expected/rotated.log.gz.zb.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn compressed_without_z() -> TestResult {
+    run_binary(&["tests/inputs/fox.txt.gz"], "tests/inputs/fox.txt.gz")
+}
+
+// --------------------------------------------------
+#[test]
+fn headers() -> TestResult {
+    run(&["-H", FOX, EMPTY, SPIDERS], "tests/expected/all.H.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn headers_stdin() -> TestResult {
+    let input = fs::read_to_string(FOX)?;
+    Command::cargo_bin(PRG)?
+        .args(["--headers", "-"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(format!("==> standard input <==\n{}", input));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn header_counts() -> TestResult {
+    run(
+        &["-H", "--header-counts", "-b", FOX, BUSTLE],
+        "tests/expected/all.H.counts.b.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_counts_lines() -> TestResult {
+    run(
+        &["-H", "--header-counts", "--lines", "2:3", BUSTLE, FOX],
+        "tests/expected/all.H.counts.lines.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_counts_stdin() -> TestResult {
+    run_stdin(
+        BUSTLE,
+        &["-H", "--header-counts", "--lines", "2:3", "-", FOX],
+        "tests/expected/all.H.counts.stdin.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_counts_bytes() -> TestResult {
+    run(
+        &["-H", "--header-counts", "--bytes", "5:19", FOX, SPIDERS],
+        "tests/expected/all.H.counts.bytes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn header_template() -> TestResult {
+    run(
+        &[
+            "--header-template=--- {path}: {lines} lines, {bytes} bytes",
+            SPIDERS,
+        ],
+        "tests/expected/spiders.txt.template.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn lines() -> TestResult {
+    run(
+        &["--lines", "2:3", "-n", BUSTLE, SPIDERS],
+        "tests/expected/all.lines.n.out",
+    )?;
+    run(
+        &["--lines", "6:", BUSTLE],
+        "tests/expected/the-bustle.txt.lines.out",
+    )?;
+    run_stdin(
+        BUSTLE,
+        &["--lines=6:", "-"],
+        "tests/expected/the-bustle.txt.lines.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn bytes() -> TestResult {
+    run(
+        &["--bytes", "5:19", FOX],
+        "tests/expected/fox.txt.bytes.out",
+    )?;
+    run_stdin(FOX, &["--bytes=5:19"], "tests/expected/fox.txt.bytes.out")?;
+    run(
+        &["-z", "--bytes", "5:19", "tests/inputs/fox.txt.gz"],
+        "tests/expected/fox.txt.bytes.out",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn range_past_end() -> TestResult {
+    run(&["--lines", "5:10", FOX], "tests/expected/empty.txt.out")?;
+    run(&["--bytes", "100:", FOX], "tests/expected/empty.txt.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn invalid_ranges() -> TestResult {
+    for range in ["0:3", "4:3", "a:b", ""] {
+        Command::cargo_bin(PRG)?
+            .args(["--lines", range, FOX])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("invalid value"));
+    }
+    Ok(())
+}
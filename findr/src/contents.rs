@@ -0,0 +1,107 @@
+//! The -contains predicate, which searches the contents of regular files for a regex.
+//!
+//! Files are read in chunks rather than all at once, and searched a line at a time as whole lines
+//! become available, so a match can't span lines unless the buffer fills first. A line longer than
+//! the buffer is searched in pieces of the buffer's size. Files containing a NUL byte are taken to
+//! be binary and never match, as with grep.
+
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use regex::bytes::{Regex, RegexBuilder};
+
+use crate::errors::Errors;
+use crate::Result;
+
+/// The amount read from the file at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The most that is buffered while waiting for the end of a line.
+const MAX_BUFFER: usize = 1024 * 1024;
+
+#[derive(Debug)]
+pub(crate) struct Contains(Regex);
+
+impl Contains {
+    /// Compile the pattern, in which `^` and `$` match at the start and end of each line.
+    pub(crate) fn new(pattern: &str) -> Result<Contains> {
+        Ok(Contains(
+            RegexBuilder::new(pattern).multi_line(true).build()?,
+        ))
+    }
+
+    /// True if the file at `path` is text and contains a match, reporting any error reading it.
+    pub(crate) fn matches(&self, path: &Path, errors: &Errors) -> bool {
+        match File::open(path).and_then(|file| self.search(file)) {
+            Ok(found) => found,
+            Err(e) => {
+                errors.report(format_args!("{}: {}", path.display(), e));
+                false
+            }
+        }
+    }
+
+    fn search(&self, mut reader: impl Read) -> io::Result<bool> {
+        let mut buffer = Vec::with_capacity(CHUNK_SIZE);
+        loop {
+            let start = buffer.len();
+            buffer.resize(start + CHUNK_SIZE, 0);
+            let count = loop {
+                match reader.read(&mut buffer[start..]) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result?,
+                }
+            };
+            buffer.truncate(start + count);
+
+            if count == 0 {
+                return Ok(self.0.is_match(&buffer));
+            }
+            if buffer[start..].contains(&0) {
+                return Ok(false);
+            }
+
+            // Search up to the end of the last complete line, unless the buffer is full.
+            let end = match buffer.iter().rposition(|&b| b == b'\n') {
+                Some(newline) => newline + 1,
+                None if buffer.len() >= MAX_BUFFER => buffer.len(),
+                None => continue,
+            };
+            if self.0.is_match(&buffer[..end]) {
+                return Ok(true);
+            }
+            buffer.drain(..end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Contains, CHUNK_SIZE};
+
+    fn search(pattern: &str, contents: &[u8]) -> bool {
+        Contains::new(pattern).unwrap().search(contents).unwrap()
+    }
+
+    #[test]
+    fn test_search() {
+        assert!(search("fn main", b"use std;\n\nfn main() {}\n"));
+        assert!(!search("fn main", b"fn mai\nn()\n"));
+        assert!(search("^b$", b"a\nb\nc"));
+        assert!(!search("a", b"a\0"));
+    }
+
+    #[test]
+    fn test_search_across_chunks() {
+        let mut contents = vec![b'x'; CHUNK_SIZE - 2];
+        contents.extend_from_slice(b"\nneedle in a haystack\n");
+        assert!(search("needle in a haystack", &contents));
+
+        let mut contents = b"text\n".to_vec();
+        contents.resize(CHUNK_SIZE * 3, b'x');
+        contents.push(0);
+        assert!(!search("needle", &contents));
+    }
+}
@@ -0,0 +1,159 @@
+//! The --duplicates mode, which reports groups of matching files with identical contents.
+//!
+//! Files are grouped by size first, which needs only the metadata the walk has already read. Files
+//! that share a size are then grouped by a hash of their first few KiB, and only those that still
+//! share a group are hashed in full. Empty files are left out, since they are trivially identical,
+//! and hard links to the same file count as one file, since they take no extra space.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, Metadata};
+use std::hash::Hash;
+use std::io;
+use std::io::{Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::prelude::OsStrExt;
+use std::path::PathBuf;
+
+use serde_json::json;
+
+use crate::errors::Errors;
+
+/// The amount of each file hashed to tell apart files of the same size.
+const PARTIAL_SIZE: u64 = 4096;
+
+/// A regular file set aside by the walk to be compared with the others.
+pub(crate) struct Candidate {
+    path: PathBuf,
+    size: u64,
+    /// The device and inode numbers, which identify hard links to the same file.
+    id: (u64, u64),
+}
+
+impl Candidate {
+    pub(crate) fn new(path: PathBuf, metadata: &Metadata) -> Candidate {
+        Candidate {
+            path,
+            size: metadata.len(),
+            id: (metadata.dev(), metadata.ino()),
+        }
+    }
+}
+
+/// Sort `candidates` into groups of two or more files with identical contents, reporting any file
+/// that can't be read. Each group is sorted by path, and the groups by their first paths.
+pub(crate) fn find(mut candidates: Vec<Candidate>, errors: &Errors) -> Vec<Vec<Candidate>> {
+    candidates.sort_unstable_by(|lhs, rhs| lhs.path.cmp(&rhs.path));
+    let mut seen = HashSet::new();
+    candidates.retain(|candidate| candidate.size > 0 && seen.insert(candidate.id));
+
+    let mut duplicates = Vec::new();
+    for same_size in group_by(candidates, |candidate| Some(candidate.size)) {
+        for same_start in group_by(same_size, |candidate| hash(candidate, PARTIAL_SIZE, errors)) {
+            if same_start[0].size <= PARTIAL_SIZE {
+                duplicates.push(same_start);
+            } else {
+                duplicates.extend(group_by(same_start, |candidate| {
+                    hash(candidate, u64::MAX, errors)
+                }));
+            }
+        }
+    }
+
+    for group in &mut duplicates {
+        group.sort_unstable_by(|lhs, rhs| lhs.path.cmp(&rhs.path));
+    }
+    duplicates.sort_unstable_by(|lhs, rhs| lhs[0].path.cmp(&rhs[0].path));
+    duplicates
+}
+
+/// Write each group of duplicates as its paths, one per line, with a blank line between groups, or
+/// with `json`, as an object with the size of the files and an array of their paths.
+pub(crate) fn write(out: &mut impl Write, groups: &[Vec<Candidate>], json: bool) -> io::Result<()> {
+    for (i, group) in groups.iter().enumerate() {
+        if json {
+            let object = json!({
+                "size": group[0].size,
+                "paths": group
+                    .iter()
+                    .map(|candidate| candidate.path.to_string_lossy())
+                    .collect::<Vec<_>>(),
+            });
+            serde_json::to_writer(&mut *out, &object)?;
+            out.write_all(b"\n")?;
+            continue;
+        }
+
+        if i > 0 {
+            out.write_all(b"\n")?;
+        }
+        for candidate in group {
+            out.write_all(candidate.path.as_os_str().as_bytes())?;
+            out.write_all(b"\n")?;
+        }
+    }
+    Ok(())
+}
+
+/// Split `candidates` into groups sharing the same key, keeping only groups of two or more.
+/// Candidates without a key are dropped.
+fn group_by<K: Hash + Eq>(
+    candidates: Vec<Candidate>,
+    mut key: impl FnMut(&Candidate) -> Option<K>,
+) -> Vec<Vec<Candidate>> {
+    let mut groups: HashMap<K, Vec<Candidate>> = HashMap::new();
+    for candidate in candidates {
+        if let Some(key) = key(&candidate) {
+            groups.entry(key).or_default().push(candidate);
+        }
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Hash up to `limit` bytes from the start of a file, or report why it couldn't be read.
+fn hash(candidate: &Candidate, limit: u64, errors: &Errors) -> Option<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    let result =
+        File::open(&candidate.path).and_then(|file| io::copy(&mut file.take(limit), &mut hasher));
+    match result {
+        Ok(_) => Some(hasher.finalize()),
+        Err(e) => {
+            errors.report(format_args!("{}: {}", candidate.path.display(), e));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{group_by, Candidate};
+
+    fn candidate(path: &str, size: u64) -> Candidate {
+        Candidate {
+            path: PathBuf::from(path),
+            size,
+            id: (0, 0),
+        }
+    }
+
+    #[test]
+    fn test_group_by() {
+        let candidates = vec![
+            candidate("a", 1),
+            candidate("b", 2),
+            candidate("c", 1),
+            candidate("d", 3),
+        ];
+        let groups = group_by(candidates, |candidate| Some(candidate.size));
+        assert_eq!(groups.len(), 1);
+        let paths: Vec<_> = groups[0].iter().map(|c| c.path.to_str().unwrap()).collect();
+        assert_eq!(paths, ["a", "c"]);
+
+        let candidates = vec![candidate("a", 1), candidate("b", 1)];
+        assert!(group_by(candidates, |_| None::<u64>).is_empty());
+    }
+}
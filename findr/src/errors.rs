@@ -0,0 +1,41 @@
+//! Errors that findr reports and carries on past, such as a directory it can't read or a command it
+//! can't run. As with find, any such error makes findr exit unsuccessfully once it's done.
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Counts the errors reported during a run, printing each unless --quiet-errors was given.
+pub(crate) struct Errors {
+    count: AtomicUsize,
+    quiet: bool,
+}
+
+impl Errors {
+    pub(crate) fn new(quiet: bool) -> Errors {
+        Errors {
+            count: AtomicUsize::new(0),
+            quiet,
+        }
+    }
+
+    pub(crate) fn report(&self, message: impl Display) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if !self.quiet {
+            eprintln!("{}", message);
+        }
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Print the number of errors reported, if there were any and they weren't silenced.
+    pub(crate) fn summarize(&self) {
+        match self.count() {
+            0 => {}
+            _ if self.quiet => {}
+            1 => eprintln!("findr: 1 error"),
+            count => eprintln!("findr: {} errors", count),
+        }
+    }
+}
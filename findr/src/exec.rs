@@ -0,0 +1,159 @@
+//! The -exec action, which runs a command once per matched entry (`-exec cmd {} ;`) or once per
+//! batch of matched entries (`-exec cmd {} +`).
+
+use std::ffi::{OsStr, OsString};
+use std::io::Write;
+use std::os::unix::prelude::{OsStrExt, OsStringExt};
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+use std::sync::Mutex;
+use std::{io, mem};
+
+use crate::errors::Errors;
+use crate::Result;
+
+const PLACEHOLDER: &[u8] = b"{}";
+
+/// The number of bytes of paths to accumulate before running a batch, kept well below `ARG_MAX` so
+/// that the command line always fits alongside the environment.
+const MAX_BATCH_BYTES: usize = 128 * 1024;
+
+#[derive(Debug)]
+pub(crate) struct Exec {
+    /// The command and its arguments. For batches, the trailing `{}` is stripped, since the paths
+    /// are always appended.
+    command: Vec<OsString>,
+    /// The paths awaiting a batched invocation, or `None` if the command runs once per entry.
+    batch: Option<Mutex<Batch>>,
+}
+
+#[derive(Debug, Default)]
+struct Batch {
+    paths: Vec<OsString>,
+    bytes: usize,
+}
+
+impl Exec {
+    /// Consume the tokens of an -exec action up to and including its terminator: `;`, or `+`
+    /// immediately following a `{}`.
+    pub(crate) fn parse(tokens: &mut impl Iterator<Item = OsString>) -> Result<Exec> {
+        let mut command: Vec<OsString> = Vec::new();
+        for token in tokens {
+            if is_terminator(command.last(), &token) {
+                if token == ";" {
+                    return Exec::new(command, None);
+                }
+                command.pop();
+                return Exec::new(command, Some(Mutex::default()));
+            }
+            command.push(token);
+        }
+        Err("missing argument to '-exec'".into())
+    }
+
+    fn new(command: Vec<OsString>, batch: Option<Mutex<Batch>>) -> Result<Exec> {
+        if command.is_empty() {
+            return Err("missing argument to '-exec'".into());
+        }
+        if batch.is_some() && command.iter().any(|arg| is_placeholder(arg)) {
+            return Err("only one instance of {} is supported with -exec ... +".into());
+        }
+        Ok(Exec { command, batch })
+    }
+
+    /// Run the command for `path`, returning true if it exited successfully. As with find, a
+    /// command that exits unsuccessfully only makes -exec false, and isn't reported as an error.
+    /// Batched commands are deferred and always return true.
+    pub(crate) fn call(&self, path: &Path, errors: &Errors) -> bool {
+        let Some(batch) = &self.batch else {
+            let args = self
+                .command
+                .iter()
+                .map(|arg| replace_placeholder(arg, path.as_os_str()));
+            return self
+                .spawn(args, errors)
+                .is_some_and(|status| status.success());
+        };
+
+        let mut batch = batch.lock().unwrap();
+        batch.bytes += path.as_os_str().len() + 1;
+        batch.paths.push(path.as_os_str().to_owned());
+        if batch.bytes >= MAX_BATCH_BYTES {
+            let paths = mem::take(&mut *batch).paths;
+            self.spawn_batch(paths, errors);
+        }
+        true
+    }
+
+    /// Run any outstanding batch.
+    pub(crate) fn flush(&self, errors: &Errors) {
+        if let Some(batch) = &self.batch {
+            let paths = mem::take(&mut *batch.lock().unwrap()).paths;
+            if !paths.is_empty() {
+                self.spawn_batch(paths, errors);
+            }
+        }
+    }
+
+    /// Run the command for a batch of paths, reporting it if it exits unsuccessfully.
+    fn spawn_batch(&self, paths: Vec<OsString>, errors: &Errors) {
+        let args = self.command.iter().cloned().chain(paths);
+        if self
+            .spawn(args, errors)
+            .is_some_and(|status| !status.success())
+        {
+            errors.report(format_args!(
+                "-exec {}: command failed",
+                self.program().to_string_lossy()
+            ));
+        }
+    }
+
+    /// Run the command to completion, reporting any failure to start it.
+    fn spawn(
+        &self,
+        mut args: impl Iterator<Item = OsString>,
+        errors: &Errors,
+    ) -> Option<ExitStatus> {
+        let program = args.next()?;
+        // Anything printed so far must appear before the command's own output.
+        let _ = io::stdout().flush();
+        match Command::new(&program).args(args).status() {
+            Ok(status) => Some(status),
+            Err(e) => {
+                errors.report(format_args!("{}: {}", program.to_string_lossy(), e));
+                None
+            }
+        }
+    }
+
+    fn program(&self) -> &OsStr {
+        &self.command[0]
+    }
+}
+
+/// True if `token` ends an -exec command, given the token before it.
+pub(crate) fn is_terminator(prev: Option<&OsString>, token: &OsStr) -> bool {
+    token == ";" || (token == "+" && prev.is_some_and(|prev| is_placeholder(prev)))
+}
+
+fn is_placeholder(arg: &OsStr) -> bool {
+    arg.as_bytes() == PLACEHOLDER
+}
+
+/// Replace every occurrence of `{}` in `arg` with `path`, as GNU find does for `-exec ... ;`.
+fn replace_placeholder(arg: &OsStr, path: &OsStr) -> OsString {
+    let arg = arg.as_bytes();
+    let mut replaced = Vec::with_capacity(arg.len());
+    let mut i = 0;
+    while i < arg.len() {
+        if arg[i..].starts_with(PLACEHOLDER) {
+            replaced.extend_from_slice(path.as_bytes());
+            i += PLACEHOLDER.len();
+        } else {
+            replaced.push(arg[i]);
+            i += 1;
+        }
+    }
+    OsString::from_vec(replaced)
+}
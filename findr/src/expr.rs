@@ -0,0 +1,773 @@
+//! A find-style expression language.
+//!
+//! Primaries such as `-name` and `-type` are combined with `!`, `-a`, `-o` and parentheses into a
+//! predicate tree, which is evaluated against each entry in the walk. As in `find`, `-a` binds more
+//! tightly than `-o`, and two primaries with no operator between them are implicitly ANDed.
+//!
+//! Actions such as `-print` and `-exec` are primaries with side effects. If an expression contains
+//! no actions other than `-prune`, it is treated as `( expr ) -print`.
+
+use std::ffi::OsString;
+use std::fs::Metadata;
+use std::io;
+use std::io::Write;
+use std::iter::Peekable;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::prelude::OsStrExt;
+use std::time::SystemTime;
+use std::vec;
+
+use crate::contents::Contains;
+use crate::errors::Errors;
+use crate::exec::{self, Exec};
+use crate::glob::Glob;
+use crate::listing;
+use crate::printf::Format;
+use crate::stat::{self, Perm, TimeField};
+use crate::walk::Entry;
+use crate::{EntryType, Result};
+use clap::ValueEnum;
+use regex::bytes::Regex;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug)]
+pub(crate) enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    /// True if the file name matches the glob.
+    Name(Glob),
+    /// True if the full path, including the starting point, matches the glob.
+    Path(Glob),
+    /// True if the file name contains a match for the regex. Used by the --name option.
+    NameRegex(Regex),
+    /// True if the whole of the full path matches the regex.
+    Regex(Regex),
+    /// True if the file is of any of the given types.
+    Type(Vec<EntryType>),
+    /// Like `Type`, but tests the target of a link that isn't followed, or the link itself if it
+    /// is.
+    XType(Vec<EntryType>),
+    /// True if the file size, rounded up to a whole number of `unit`s, satisfies the comparison.
+    Size {
+        size: Comparison,
+        unit: u64,
+    },
+    /// True if the file was last accessed, changed or modified a number of whole days ago that
+    /// satisfies the comparison. Ages are measured from the moment the expression was parsed.
+    Time {
+        field: TimeField,
+        days: Comparison,
+        now: SystemTime,
+    },
+    /// True if the file was modified more recently than the reference time.
+    Newer(SystemTime),
+    /// True if the file's permission bits match.
+    Perm(Perm),
+    /// True if the file is owned by the user ID.
+    User(u32),
+    /// True if the file is owned by the group ID.
+    Group(u32),
+    /// True if the file is an empty regular file or directory.
+    Empty,
+    /// True if the file's inode number satisfies the comparison.
+    Inum(Comparison),
+    /// True if the file is a regular text file containing a match for the regex.
+    Contains(Contains),
+    /// Always true. Prevents descent into the current entry if it is a directory.
+    Prune,
+    /// Always true. Prints the path of the current entry, followed by a newline.
+    Print,
+    /// Always true. Prints the path of the current entry, followed by a NUL byte.
+    Print0,
+    /// Always true. Prints the current entry according to a format string.
+    Printf(Format),
+    /// Always true. Prints the current entry in the long listing format of `find -ls`.
+    Ls,
+    /// Always true. Prints the current entry as a JSON object. Replaces `Print` with --json.
+    Json,
+    /// Always true. Has the walk set the current entry aside to be compared with the others.
+    /// Replaces `Print` with --duplicates.
+    Collect,
+    /// Runs a command for the current entry. See [Exec::call].
+    Exec(Exec),
+    /// Always true. Ends the walk once the current entry's output has been written, without
+    /// evaluating the rest of the expression.
+    Quit,
+    /// Always true. Has the walk remove the current entry, or for a directory, remove it once its
+    /// contents have been visited.
+    Delete,
+}
+
+/// Per-entry state through which actions communicate with the walk.
+pub(crate) struct Context<'a> {
+    /// Set if the walk should not descend into the current entry.
+    pub(crate) prune: bool,
+    /// Set if the walk should remove the current entry.
+    pub(crate) delete: bool,
+    /// Set if the walk should end after the current entry.
+    pub(crate) quit: bool,
+    /// Set if the walk should set the current entry aside for --duplicates.
+    pub(crate) collect: bool,
+    /// Set if an action other than -prune or -quit ran for the current entry, which makes it one
+    /// of the results counted by --max-results.
+    pub(crate) acted: bool,
+    /// An error writing output, which ends the walk.
+    pub(crate) error: Option<io::Error>,
+    /// Output printed for the current entry, written by the walk once evaluation is complete so
+    /// that output from concurrent threads isn't interleaved.
+    pub(crate) output: Vec<u8>,
+    /// Set if output must be held until the walk is over, so it can be sorted.
+    deferred: bool,
+    errors: &'a Errors,
+}
+
+impl<'a> Context<'a> {
+    pub(crate) fn new(deferred: bool, errors: &'a Errors) -> Context<'a> {
+        Context {
+            prune: false,
+            delete: false,
+            quit: false,
+            collect: false,
+            acted: false,
+            error: None,
+            output: Vec::new(),
+            deferred,
+            errors,
+        }
+    }
+
+    /// Write out the output printed so far, unless it is deferred.
+    fn flush_output(&mut self) {
+        if self.deferred || self.output.is_empty() {
+            return;
+        }
+        if let Err(e) = io::stdout().lock().write_all(&self.output) {
+            self.error.get_or_insert(e);
+        }
+        self.output.clear();
+    }
+}
+
+impl Expr {
+    /// Parse the tokens of an expression, returning `None` if there are none.
+    pub(crate) fn parse(tokens: Vec<OsString>) -> Result<Option<Expr>> {
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+
+        let mut parser = Parser {
+            tokens: tokens.into_iter().peekable(),
+        };
+        let expr = parser.or()?;
+        match parser.tokens.next() {
+            None => Ok(Some(expr)),
+            Some(token) => Err(format!("unexpected '{}'", token.to_string_lossy()).into()),
+        }
+    }
+
+    /// Wrap an expression in the implicit `-print` action if it lacks an action of its own. An
+    /// absent expression prints every entry.
+    pub(crate) fn with_default_action(expr: Option<Expr>) -> Expr {
+        match expr {
+            Some(expr) if expr.has_action() => expr,
+            Some(expr) => Expr::And(Box::new(expr), Box::new(Expr::Print)),
+            None => Expr::Print,
+        }
+    }
+
+    /// Replace every `-print`, including the implicit one, with `action`, as --json and
+    /// --duplicates require.
+    pub(crate) fn replace_print(self, action: fn() -> Expr) -> Expr {
+        match self {
+            Expr::And(lhs, rhs) => Expr::And(
+                Box::new(lhs.replace_print(action)),
+                Box::new(rhs.replace_print(action)),
+            ),
+            Expr::Or(lhs, rhs) => Expr::Or(
+                Box::new(lhs.replace_print(action)),
+                Box::new(rhs.replace_print(action)),
+            ),
+            Expr::Not(expr) => Expr::Not(Box::new(expr.replace_print(action))),
+            Expr::Print => action(),
+            expr => expr,
+        }
+    }
+
+    /// Combine two optional expressions with an implicit AND.
+    pub(crate) fn and(lhs: Option<Expr>, rhs: Option<Expr>) -> Option<Expr> {
+        match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) => Some(Expr::And(Box::new(lhs), Box::new(rhs))),
+            (lhs, rhs) => lhs.or(rhs),
+        }
+    }
+
+    /// Combine a sequence of expressions with OR, returning `None` if the sequence is empty.
+    pub(crate) fn any(exprs: impl IntoIterator<Item = Expr>) -> Option<Expr> {
+        exprs
+            .into_iter()
+            .reduce(|lhs, rhs| Expr::Or(Box::new(lhs), Box::new(rhs)))
+    }
+
+    /// Evaluate the expression against `entry`, short-circuiting `-a` and `-o` as `find` does, so
+    /// actions on the right of an operator run only if the left side requires it.
+    pub(crate) fn matches(&self, entry: &Entry, context: &mut Context) -> bool {
+        match self {
+            // Nothing is evaluated once -quit has been reached.
+            Expr::And(lhs, rhs) => {
+                lhs.matches(entry, context) && !context.quit && rhs.matches(entry, context)
+            }
+            Expr::Or(lhs, rhs) => {
+                lhs.matches(entry, context) || (!context.quit && rhs.matches(entry, context))
+            }
+            Expr::Not(expr) => !expr.matches(entry, context),
+            Expr::Name(glob) => glob.is_match(entry.file_name().as_bytes()),
+            Expr::Path(glob) => glob.is_match(entry.path().as_os_str().as_bytes()),
+            Expr::NameRegex(pattern) => pattern.is_match(entry.file_name().as_bytes()),
+            Expr::Regex(pattern) => pattern.is_match(entry.path().as_os_str().as_bytes()),
+            Expr::Type(types) => types.iter().any(|t| t.matches(entry.file_type())),
+            Expr::XType(types) => {
+                let file_type = entry.xtype();
+                types.iter().any(|t| t.matches(file_type))
+            }
+            Expr::Size { size, unit } => {
+                with_metadata(entry, |md| size.matches(md.len().div_ceil(*unit)))
+            }
+            Expr::Time { field, days, now } => {
+                with_metadata(entry, |md| days.matches(days_between(field.of(md), *now)))
+            }
+            Expr::Newer(reference) => {
+                with_metadata(entry, |md| TimeField::Modified.of(md) > *reference)
+            }
+            Expr::Perm(perm) => with_metadata(entry, |md| perm.matches(md)),
+            Expr::User(uid) => with_metadata(entry, |md| md.uid() == *uid),
+            Expr::Group(gid) => with_metadata(entry, |md| md.gid() == *gid),
+            Expr::Empty => with_metadata(entry, |md| stat::is_empty(entry.path(), md)),
+            Expr::Inum(inum) => with_metadata(entry, |md| inum.matches(md.ino())),
+            Expr::Contains(contains) => {
+                entry.file_type().is_file() && contains.matches(entry.path(), context.errors)
+            }
+            Expr::Prune => {
+                context.prune = true;
+                true
+            }
+            Expr::Delete => {
+                context.delete = true;
+                context.acted = true;
+                true
+            }
+            Expr::Collect => {
+                context.collect = true;
+                context.acted = true;
+                true
+            }
+            Expr::Quit => {
+                context.quit = true;
+                true
+            }
+            // Paths are written as raw bytes, so names that aren't valid UTF-8 survive intact.
+            Expr::Print => print(context, entry, |out| {
+                out.write_all(entry.path().as_os_str().as_bytes())?;
+                out.write_all(b"\n")
+            }),
+            Expr::Print0 => print(context, entry, |out| {
+                out.write_all(entry.path().as_os_str().as_bytes())?;
+                out.write_all(b"\0")
+            }),
+            Expr::Printf(format) => print(context, entry, |out| format.write(out, entry)),
+            Expr::Ls => print(context, entry, |out| listing::write_ls(out, entry)),
+            Expr::Json => {
+                let errors = context.errors;
+                print(context, entry, |out| {
+                    listing::write_json(out, entry, errors)
+                })
+            }
+            Expr::Exec(exec) => {
+                context.flush_output();
+                context.acted = true;
+                exec.call(entry.path(), context.errors)
+            }
+        }
+    }
+
+    /// Reorder each chain of `-a` operands so that cheaper tests run first, and the more expensive
+    /// ones only for entries that pass them. Operands are never moved across an action or
+    /// `-prune`, so the order of side effects is unchanged.
+    pub(crate) fn optimize(self) -> Expr {
+        match self {
+            Expr::And(..) => {
+                let mut operands = Vec::new();
+                self.flatten_and(&mut operands);
+                for run in operands.split_mut(|operand| !operand.is_pure()) {
+                    run.sort_by_key(Expr::cost);
+                }
+                operands
+                    .into_iter()
+                    .reduce(|lhs, rhs| Expr::And(Box::new(lhs), Box::new(rhs)))
+                    .unwrap()
+            }
+            Expr::Or(lhs, rhs) => Expr::Or(Box::new(lhs.optimize()), Box::new(rhs.optimize())),
+            Expr::Not(expr) => Expr::Not(Box::new(expr.optimize())),
+            expr => expr,
+        }
+    }
+
+    /// Collect the operands of a chain of `-a`, optimizing each.
+    fn flatten_and(self, operands: &mut Vec<Expr>) {
+        match self {
+            Expr::And(lhs, rhs) => {
+                lhs.flatten_and(operands);
+                rhs.flatten_and(operands);
+            }
+            expr => operands.push(expr.optimize()),
+        }
+    }
+
+    /// A rough measure of the work needed to evaluate the expression: 0 for tests of the path and
+    /// type alone, 1 for those that need the file's metadata, and 2 for those that read it.
+    fn cost(&self) -> u8 {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => lhs.cost().max(rhs.cost()),
+            Expr::Not(expr) => expr.cost(),
+            Expr::Name(_) | Expr::Path(_) | Expr::NameRegex(_) | Expr::Regex(_) | Expr::Type(_) => {
+                0
+            }
+            Expr::Contains(_) => 2,
+            _ => 1,
+        }
+    }
+
+    /// True if evaluating the expression has no side effects, so it may be reordered.
+    fn is_pure(&self) -> bool {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => lhs.is_pure() && rhs.is_pure(),
+            Expr::Not(expr) => expr.is_pure(),
+            Expr::Prune | Expr::Quit => false,
+            expr => !expr.has_action(),
+        }
+    }
+
+    /// True if the expression contains an action that disables the implicit `-print`.
+    fn has_action(&self) -> bool {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => lhs.has_action() || rhs.has_action(),
+            Expr::Not(expr) => expr.has_action(),
+            Expr::Print
+            | Expr::Print0
+            | Expr::Printf(_)
+            | Expr::Ls
+            | Expr::Json
+            | Expr::Collect
+            | Expr::Exec(_)
+            | Expr::Delete => true,
+            _ => false,
+        }
+    }
+
+    /// Run any batched `-exec ... +` commands with the paths collected so far.
+    pub(crate) fn flush(&self, errors: &Errors) {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => {
+                lhs.flush(errors);
+                rhs.flush(errors);
+            }
+            Expr::Not(expr) => expr.flush(errors),
+            Expr::Exec(exec) => exec.flush(errors),
+            _ => {}
+        }
+    }
+
+    /// True if the expression contains -delete.
+    pub(crate) fn deletes(&self) -> bool {
+        match self {
+            Expr::And(lhs, rhs) | Expr::Or(lhs, rhs) => lhs.deletes() || rhs.deletes(),
+            Expr::Not(expr) => expr.deletes(),
+            expr => matches!(expr, Expr::Delete),
+        }
+    }
+}
+
+/// Write to the entry's output, returning false if it couldn't be described. Since the output is
+/// in memory, that can only be because the entry's metadata couldn't be read, which is reported
+/// without ending the walk, and the entry left out rather than written in part.
+fn print(
+    context: &mut Context,
+    entry: &Entry,
+    write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>,
+) -> bool {
+    let len = context.output.len();
+    match write(&mut context.output) {
+        Ok(()) => {
+            context.acted = true;
+            true
+        }
+        Err(e) => {
+            context.output.truncate(len);
+            context
+                .errors
+                .report(format_args!("{}: {}", entry.path().display(), e));
+            false
+        }
+    }
+}
+
+/// Evaluate a predicate against the entry's metadata, which is false if the metadata can't be read.
+fn with_metadata(entry: &Entry, predicate: impl FnOnce(&Metadata) -> bool) -> bool {
+    entry.metadata().is_ok_and(predicate)
+}
+
+/// The number of whole days elapsed between `then` and `now`. Times in the future are zero days
+/// old.
+fn days_between(then: SystemTime, now: SystemTime) -> u64 {
+    now.duration_since(then)
+        .map(|age| age.as_secs() / SECONDS_PER_DAY)
+        .unwrap_or(0)
+}
+
+/// A numeric argument in `find` notation: `+N` for more than N, `-N` for less than N, and `N` for
+/// exactly N.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum Comparison {
+    Less(u64),
+    Exactly(u64),
+    Greater(u64),
+}
+
+impl Comparison {
+    fn matches(self, n: u64) -> bool {
+        match self {
+            Comparison::Less(target) => n < target,
+            Comparison::Exactly(target) => n == target,
+            Comparison::Greater(target) => n > target,
+        }
+    }
+
+    /// Split the comparison prefix from `arg`, returning the remainder for further parsing.
+    fn split(arg: &str) -> (fn(u64) -> Comparison, &str) {
+        if let Some(rest) = arg.strip_prefix('+') {
+            (Comparison::Greater, rest)
+        } else if let Some(rest) = arg.strip_prefix('-') {
+            (Comparison::Less, rest)
+        } else {
+            (Comparison::Exactly, arg)
+        }
+    }
+}
+
+/// The number of operands taken by each primary, other than `-exec`, whose command runs until a
+/// terminator.
+fn primary_arity(token: &str) -> Option<usize> {
+    match token {
+        "-prune" | "-print" | "-print0" | "-ls" | "-empty" | "-delete" | "-quit" => Some(0),
+        "-name" | "-iname" | "-path" | "-ipath" | "-regex" | "-iregex" | "-type" | "-xtype"
+        | "-size" | "-atime" | "-ctime" | "-mtime" | "-newer" | "-perm" | "-user" | "-group"
+        | "-inum" | "-contains" | "-printf" => Some(1),
+        _ => None,
+    }
+}
+
+fn is_operator(token: &str) -> bool {
+    matches!(
+        token,
+        "!" | "-not" | "-a" | "-and" | "-o" | "-or" | "(" | ")"
+    )
+}
+
+/// Separate the tokens of a find-style expression from the arguments handled by clap.
+///
+/// Expression tokens may be interleaved with paths and options, so each recognised primary carries
+/// its operands with it, even when an operand looks like an option (e.g. `-size -10k`). Options that
+/// find spells as primaries, such as `-xdev`, are passed to clap under their long names.
+pub(crate) fn partition(
+    args: impl IntoIterator<Item = OsString>,
+) -> (Vec<OsString>, Vec<OsString>) {
+    let mut clap_args = Vec::new();
+    let mut expr_tokens = Vec::new();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let token = arg.to_str().unwrap_or_default();
+        if let Some(arity) = primary_arity(token) {
+            expr_tokens.push(arg);
+            expr_tokens.extend(args.by_ref().take(arity));
+        } else if token == "-exec" {
+            expr_tokens.push(arg);
+            let mut prev = None;
+            for arg in args.by_ref() {
+                let end = exec::is_terminator(prev.as_ref(), &arg);
+                expr_tokens.push(arg.clone());
+                if end {
+                    break;
+                }
+                prev = Some(arg);
+            }
+        } else if is_operator(token) {
+            expr_tokens.push(arg);
+        } else if token == "-xdev" || token == "-mount" {
+            clap_args.push("--one-file-system".into());
+        } else {
+            clap_args.push(arg);
+        }
+    }
+
+    (clap_args, expr_tokens)
+}
+
+/// A recursive-descent parser over the grammar:
+///
+/// ```text
+/// or      := and { ("-o" | "-or") and }
+/// and     := unary { ["-a" | "-and"] unary }
+/// unary   := ("!" | "-not") unary | "(" or ")" | primary
+/// ```
+struct Parser {
+    tokens: Peekable<vec::IntoIter<OsString>>,
+}
+
+impl Parser {
+    fn peek(&mut self) -> Option<&str> {
+        self.tokens
+            .peek()
+            .map(|token| token.to_str().unwrap_or_default())
+    }
+
+    fn or(&mut self) -> Result<Expr> {
+        let mut expr = self.and()?;
+        while let Some("-o" | "-or") = self.peek() {
+            self.tokens.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        loop {
+            match self.peek() {
+                Some("-a" | "-and") => {
+                    self.tokens.next();
+                }
+                // Anything other than the end of a group or an OR starts an implicitly ANDed term.
+                Some(")" | "-o" | "-or") | None => return Ok(expr),
+                Some(_) => {}
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        let token = self.tokens.next().ok_or("expected an expression")?;
+        match token.to_str().unwrap_or_default() {
+            "!" | "-not" => Ok(Expr::Not(Box::new(self.unary()?))),
+            "(" => {
+                let expr = self.or()?;
+                match self.tokens.next() {
+                    Some(token) if token == ")" => Ok(expr),
+                    _ => Err("expected ')'".into()),
+                }
+            }
+            primary => self.primary(primary),
+        }
+    }
+
+    fn primary(&mut self, primary: &str) -> Result<Expr> {
+        let expr = match primary {
+            "-contains" => Expr::Contains(Contains::new(&self.operand(primary)?)?),
+            "-prune" => Expr::Prune,
+            "-quit" => Expr::Quit,
+            "-ls" => Expr::Ls,
+            "-delete" => Expr::Delete,
+            "-print" => Expr::Print,
+            "-print0" => Expr::Print0,
+            "-printf" => Expr::Printf(Format::parse(&self.operand(primary)?)?),
+            "-exec" => Expr::Exec(Exec::parse(&mut self.tokens)?),
+            "-name" => Expr::Name(Glob::new(&self.operand(primary)?, false)),
+            "-iname" => Expr::Name(Glob::new(&self.operand(primary)?, true)),
+            "-path" => Expr::Path(Glob::new(&self.operand(primary)?, false)),
+            "-ipath" => Expr::Path(Glob::new(&self.operand(primary)?, true)),
+            "-regex" => Expr::Regex(whole_match_regex(&self.operand(primary)?, false)?),
+            "-iregex" => Expr::Regex(whole_match_regex(&self.operand(primary)?, true)?),
+            "-type" => Expr::Type(parse_types(&self.operand(primary)?)?),
+            "-xtype" => Expr::XType(parse_types(&self.operand(primary)?)?),
+            "-size" => parse_size(&self.operand(primary)?)?,
+            "-atime" => self.time(primary, TimeField::Accessed)?,
+            "-ctime" => self.time(primary, TimeField::Changed)?,
+            "-mtime" => self.time(primary, TimeField::Modified)?,
+            "-newer" => Expr::Newer(stat::reference_time(&self.operand(primary)?)?),
+            "-perm" => Expr::Perm(Perm::parse(&self.operand(primary)?)?),
+            "-user" => Expr::User(stat::parse_uid(&self.operand(primary)?)?),
+            "-group" => Expr::Group(stat::parse_gid(&self.operand(primary)?)?),
+            "-empty" => Expr::Empty,
+            "-inum" => Expr::Inum(parse_comparison(&self.operand(primary)?)?),
+            _ => return Err(format!("unexpected '{}'", primary).into()),
+        };
+        Ok(expr)
+    }
+
+    fn time(&mut self, primary: &str, field: TimeField) -> Result<Expr> {
+        Ok(Expr::Time {
+            field,
+            days: parse_comparison(&self.operand(primary)?)?,
+            now: SystemTime::now(),
+        })
+    }
+
+    fn operand(&mut self, primary: &str) -> Result<String> {
+        let operand = self
+            .tokens
+            .next()
+            .ok_or_else(|| format!("missing argument to '{}'", primary))?;
+        let operand = operand
+            .into_string()
+            .map_err(|_| format!("invalid UTF-8 in argument to '{}'", primary))?;
+        Ok(operand)
+    }
+}
+
+/// Compile a regex that must match the whole of the text, as -regex requires.
+fn whole_match_regex(pattern: &str, case_insensitive: bool) -> Result<Regex> {
+    let flags = if case_insensitive { "(?i)" } else { "" };
+    Ok(Regex::new(&format!("{}^(?:{})$", flags, pattern))?)
+}
+
+/// Parse a comma-separated list of type letters, e.g. `f,l`.
+fn parse_types(arg: &str) -> Result<Vec<EntryType>> {
+    arg.split(',')
+        .map(|letter| {
+            EntryType::from_str(letter, false)
+                .map_err(|_| format!("unknown argument to -type: {}", letter).into())
+        })
+        .collect()
+}
+
+fn parse_comparison(arg: &str) -> Result<Comparison> {
+    let (comparison, n) = Comparison::split(arg);
+    let n = n
+        .parse()
+        .map_err(|_| format!("invalid numeric argument '{}'", arg))?;
+    Ok(comparison(n))
+}
+
+/// Parse a `-size` argument, `[+-]N[bcwkMG]`. As in `find`, the default unit is the 512-byte block.
+fn parse_size(arg: &str) -> Result<Expr> {
+    let (digits, unit) = match arg.char_indices().last() {
+        Some((i, suffix)) if suffix.is_ascii_alphabetic() => (&arg[..i], size_unit(suffix)),
+        _ => (arg, Some(512)),
+    };
+    let unit = unit.ok_or_else(|| format!("invalid -size unit in '{}'", arg))?;
+    Ok(Expr::Size {
+        size: parse_comparison(digits)?,
+        unit,
+    })
+}
+
+fn size_unit(suffix: char) -> Option<u64> {
+    match suffix {
+        'b' => Some(512),
+        'c' => Some(1),
+        'w' => Some(2),
+        'k' => Some(1 << 10),
+        'M' => Some(1 << 20),
+        'G' => Some(1 << 30),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use super::{partition, Comparison, Expr};
+
+    fn tokens(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    fn parse(args: &[&str]) -> String {
+        // The Debug representation is a convenient way to assert on the shape of the tree.
+        format!("{:?}", Expr::parse(tokens(args)).unwrap().unwrap())
+    }
+
+    #[test]
+    fn test_partition() {
+        let (clap_args, expr_tokens) = partition(tokens(&[
+            "findr", "src", "-size", "-10k", "--name", "a", "!", "-type", "d", "-xdev",
+        ]));
+        assert_eq!(
+            clap_args,
+            tokens(&["findr", "src", "--name", "a", "--one-file-system"])
+        );
+        assert_eq!(expr_tokens, tokens(&["-size", "-10k", "!", "-type", "d"]));
+    }
+
+    #[test]
+    fn test_optimize() {
+        let optimized = |args: &[&str]| {
+            let expr = Expr::parse(tokens(args)).unwrap().unwrap();
+            format!("{:?}", expr.optimize())
+        };
+        assert!(optimized(&["-contains", "x", "-size", "1", "-name", "a"])
+            .starts_with("And(And(Name(Glob"));
+        assert!(
+            optimized(&["-contains", "x", "-print", "-name", "a"]).starts_with("And(And(Contains(")
+        );
+        assert!(optimized(&["!", "(", "-contains", "x", "-type", "f", ")"])
+            .starts_with("Not(And(Type("));
+    }
+
+    #[test]
+    fn test_precedence() {
+        let implicit = parse(&["-name", "a", "-name", "b", "-o", "-name", "c"]);
+        let explicit = parse(&[
+            "(", "-name", "a", "-a", "-name", "b", ")", "-or", "-name", "c",
+        ]);
+        assert_eq!(implicit, explicit);
+        assert!(implicit.starts_with("Or(And("));
+    }
+
+    #[test]
+    fn test_not() {
+        assert!(parse(&["!", "-type", "d"]).starts_with("Not(Type([Dir]))"));
+    }
+
+    #[test]
+    fn test_quit() {
+        assert_eq!(
+            parse(&["-print", "-quit"]),
+            parse(&["-print", "-a", "-quit"])
+        );
+        assert!(!Expr::Quit.is_pure());
+        assert!(!Expr::Quit.has_action());
+    }
+
+    #[test]
+    fn test_size() {
+        assert!(parse(&["-size", "+3k"]).contains("size: Greater(3), unit: 1024"));
+        assert!(parse(&["-size", "-2"]).contains("size: Less(2), unit: 512"));
+        assert!(parse(&["-size", "7c"]).contains("size: Exactly(7), unit: 1"));
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert!(Comparison::Greater(3).matches(4));
+        assert!(!Comparison::Greater(3).matches(3));
+        assert!(Comparison::Less(3).matches(2));
+        assert!(Comparison::Exactly(3).matches(3));
+    }
+
+    #[test]
+    fn test_errors() {
+        for args in [
+            &["(", "-name", "a"][..],
+            &["-name"],
+            &["-type", "x"],
+            &["-size", "3q"],
+            &["-mtime", "soon"],
+            &["-name", "a", ")"],
+            &["-o", "-name", "a"],
+        ] {
+            assert!(Expr::parse(tokens(args)).is_err(), "{:?}", args);
+        }
+    }
+}
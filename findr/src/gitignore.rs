@@ -0,0 +1,195 @@
+//! Filtering of entries excluded by `.gitignore` and `.ignore` files, as used by --ignore.
+//!
+//! Ignore files are read from each directory as the walk enters it, as well as from the ancestors
+//! of the starting point up to the root of the git repository enclosing it, so rules apply to the
+//! subtree below the file that declares them. As with git, files above the repository, or above a
+//! starting point outside any repository, are never read. Deeper files take precedence over
+//! shallower ones, and within a directory `.ignore` takes precedence over `.gitignore`. The user's
+//! global git excludes file applies everywhere, with the lowest precedence. `.git` itself is
+//! always skipped. Starting points themselves are never ignored.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+
+use crate::errors::Errors;
+use crate::walk::Entry;
+
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The directory, or in a worktree or submodule the file, that marks the root of a repository.
+const GIT_DIR: &str = ".git";
+
+/// The ignore rules in effect within a directory. Each directory shares the rules of its parent,
+/// so they are cheap to clone and can be handed to whichever thread reads the directory.
+#[derive(Clone)]
+pub(crate) struct Ignores {
+    start: Arc<Start>,
+    /// Rules declared by the directories entered on the way here, nearest first.
+    entered: Option<Arc<Level>>,
+}
+
+/// The rules that apply to a whole walk.
+struct Start {
+    /// The starting point of the walk, as given and as an absolute path. Rules are matched against
+    /// absolute paths, since they may be declared above the starting point.
+    path: PathBuf,
+    absolute_path: PathBuf,
+    global: Gitignore,
+    /// Rules declared by the ancestors of the starting point.
+    ancestors: Vec<Gitignore>,
+}
+
+/// The rules declared by one directory, linked to those of the nearest directory above it that
+/// declared any.
+struct Level {
+    gitignore: Gitignore,
+    parent: Option<Arc<Level>>,
+}
+
+/// The reason an entry was ignored.
+pub(crate) enum Reason {
+    /// A pattern matched it, declared by the given file, if it came from one.
+    Pattern {
+        pattern: String,
+        source: Option<PathBuf>,
+    },
+    /// It is a repository's `.git`.
+    GitDir,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Pattern { pattern, source } => {
+                write!(f, "ignored by '{}'", pattern)?;
+                if let Some(source) = source {
+                    write!(f, " in {}", source.display())?;
+                }
+                Ok(())
+            }
+            Reason::GitDir => write!(f, "always ignored"),
+        }
+    }
+}
+
+impl Ignores {
+    pub(crate) fn new(start: &Path, errors: &Errors) -> Ignores {
+        let absolute_path = fs::canonicalize(start).unwrap_or_else(|_| start.to_path_buf());
+        let (global, err) = Gitignore::global();
+        if let Some(err) = err {
+            errors.report(err);
+        }
+
+        // Nearer ancestors take precedence, and `ancestors` yields the nearest first.
+        let mut ancestors = Vec::new();
+        if let Some(repo) = repository_root(&absolute_path) {
+            for dir in absolute_path.ancestors().skip(1) {
+                if !dir.starts_with(repo) {
+                    break;
+                }
+                let gitignore = read_ignore_files(dir, errors);
+                if !gitignore.is_empty() {
+                    ancestors.push(gitignore);
+                }
+            }
+        }
+
+        Ignores {
+            start: Arc::new(Start {
+                path: start.to_path_buf(),
+                absolute_path,
+                global,
+                ancestors,
+            }),
+            entered: None,
+        }
+    }
+
+    /// Return the reason `entry`, found in the directory these rules belong to, is ignored, if
+    /// any.
+    pub(crate) fn check(&self, entry: &Entry) -> Option<Reason> {
+        if entry.depth() == 0 {
+            return None;
+        }
+        if entry.file_name() == GIT_DIR {
+            return Some(Reason::GitDir);
+        }
+        self.matched(&self.absolute(entry.path()), entry.file_type().is_dir())
+    }
+
+    /// The rules in effect within the directory `entry`, including its own ignore files.
+    pub(crate) fn descend(&self, entry: &Entry, errors: &Errors) -> Ignores {
+        let gitignore = read_ignore_files(&self.absolute(entry.path()), errors);
+        if gitignore.is_empty() {
+            return self.clone();
+        }
+        Ignores {
+            start: Arc::clone(&self.start),
+            entered: Some(Arc::new(Level {
+                gitignore,
+                parent: self.entered.clone(),
+            })),
+        }
+    }
+
+    fn matched(&self, path: &Path, is_dir: bool) -> Option<Reason> {
+        let entered =
+            std::iter::successors(self.entered.as_deref(), |level| level.parent.as_deref())
+                .map(|level| &level.gitignore);
+        let gitignores = entered
+            .chain(&self.start.ancestors)
+            .chain([&self.start.global]);
+
+        for gitignore in gitignores {
+            match gitignore.matched(path, is_dir) {
+                Match::None => continue,
+                Match::Whitelist(_) => return None,
+                Match::Ignore(glob) => {
+                    return Some(Reason::Pattern {
+                        pattern: glob.original().to_string(),
+                        source: glob.from().map(Path::to_path_buf),
+                    })
+                }
+            }
+        }
+        None
+    }
+
+    fn absolute(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.start.path) {
+            Ok(relative) if !relative.as_os_str().is_empty() => {
+                self.start.absolute_path.join(relative)
+            }
+            _ => self.start.absolute_path.clone(),
+        }
+    }
+}
+
+/// The root of the git repository enclosing `dir`, which may be `dir` itself.
+fn repository_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(GIT_DIR).exists())
+}
+
+/// Read the ignore files in `dir` into a single matcher. Later files take precedence, so
+/// `.ignore` overrides `.gitignore`.
+fn read_ignore_files(dir: &Path, errors: &Errors) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    for name in IGNORE_FILES {
+        let path = dir.join(name);
+        if path.is_file() {
+            if let Some(err) = builder.add(&path) {
+                errors.report(err);
+            }
+        }
+    }
+    builder.build().unwrap_or_else(|err| {
+        errors.report(err);
+        Gitignore::empty()
+    })
+}
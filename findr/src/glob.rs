@@ -0,0 +1,228 @@
+//! Shell-style glob patterns, as used by -name and -path.
+//!
+//! Patterns are matched against raw path bytes, so names that aren't valid UTF-8 can still be
+//! matched. `*` matches any sequence, including `/`, `?` matches a single character, and `[...]`
+//! matches one character from a set, negated by a leading `!` or `^`. A backslash matches the
+//! following character literally. As in `find`, a leading `.` needs no special treatment.
+
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnySequence,
+    /// `[...]`
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Glob {
+    tokens: Vec<Token>,
+    case_insensitive: bool,
+}
+
+impl Glob {
+    pub(crate) fn new(pattern: &str, case_insensitive: bool) -> Glob {
+        let pattern = if case_insensitive {
+            Cow::Owned(pattern.to_lowercase())
+        } else {
+            Cow::Borrowed(pattern)
+        };
+
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            let token = match chars[i] {
+                '*' => Token::AnySequence,
+                '?' => Token::AnyChar,
+                '[' => match parse_class(&chars[i + 1..]) {
+                    Some((class, len)) => {
+                        i += len;
+                        class
+                    }
+                    // An unterminated class is just a literal '['.
+                    None => Token::Literal('['),
+                },
+                '\\' if i + 1 < chars.len() => {
+                    i += 1;
+                    Token::Literal(chars[i])
+                }
+                c => Token::Literal(c),
+            };
+            tokens.push(token);
+            i += 1;
+        }
+
+        Glob {
+            tokens,
+            case_insensitive,
+        }
+    }
+
+    pub(crate) fn is_match(&self, text: &[u8]) -> bool {
+        let text = match (self.case_insensitive, std::str::from_utf8(text)) {
+            (false, _) => Cow::Borrowed(text),
+            (true, Ok(text)) => Cow::Owned(text.to_lowercase().into_bytes()),
+            (true, Err(_)) => Cow::Owned(text.to_ascii_lowercase()),
+        };
+        matches(&self.tokens, &text)
+    }
+}
+
+/// Parse the body of a bracket expression following the opening `[`, returning the class and the
+/// number of characters consumed, including the closing `]`.
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+    let mut i = 0;
+    let negated = matches!(chars.first(), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    // A ']' immediately after the opening bracket is a member of the set, not its end.
+    let mut first = true;
+    loop {
+        let start = match chars.get(i)? {
+            ']' if !first => return Some((Token::Class { negated, ranges }, i + 1)),
+            '\\' => {
+                i += 1;
+                *chars.get(i)?
+            }
+            &c => c,
+        };
+        first = false;
+        i += 1;
+
+        match (chars.get(i), chars.get(i + 1)) {
+            (Some('-'), Some(&end)) if end != ']' => {
+                ranges.push((start, end));
+                i += 2;
+            }
+            _ => ranges.push((start, start)),
+        }
+    }
+}
+
+/// Match `tokens` against the whole of `text`, backtracking to the most recent `*` on a mismatch.
+fn matches(tokens: &[Token], text: &[u8]) -> bool {
+    let (mut t, mut s) = (0, 0);
+    let mut backtrack = None;
+
+    while s < text.len() {
+        if let Some(token) = tokens.get(t) {
+            if *token == Token::AnySequence {
+                backtrack = Some((t, s));
+                t += 1;
+                continue;
+            }
+
+            let (c, len) = decode(&text[s..]);
+            let matched = match token {
+                Token::Literal(literal) => c == Some(*literal),
+                Token::AnyChar => true,
+                Token::Class { negated, ranges } => {
+                    let in_class =
+                        c.is_some_and(|c| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi));
+                    in_class != *negated
+                }
+                Token::AnySequence => unreachable!(),
+            };
+            if matched {
+                t += 1;
+                s += len;
+                continue;
+            }
+        }
+
+        // Let the last `*` swallow one more character and try again from there.
+        match backtrack {
+            Some((star, consumed)) => {
+                let (_, len) = decode(&text[consumed..]);
+                backtrack = Some((star, consumed + len));
+                t = star + 1;
+                s = consumed + len;
+            }
+            None => return false,
+        }
+    }
+
+    tokens[t..].iter().all(|token| *token == Token::AnySequence)
+}
+
+/// Decode the first character of `bytes`, returning `None` for a byte that doesn't begin a valid
+/// UTF-8 sequence, which is then treated as a single character of its own.
+fn decode(bytes: &[u8]) -> (Option<char>, usize) {
+    let prefix = &bytes[..bytes.len().min(4)];
+    let valid = match std::str::from_utf8(prefix) {
+        Ok(s) => s,
+        Err(e) => std::str::from_utf8(&prefix[..e.valid_up_to()]).unwrap(),
+    };
+    match valid.chars().next() {
+        Some(c) => (Some(c), c.len_utf8()),
+        None => (None, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Glob;
+
+    fn is_match(pattern: &str, text: &[u8]) -> bool {
+        Glob::new(pattern, false).is_match(text)
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(is_match("*.rs", b"lib.rs"));
+        assert!(is_match("*.rs", b".rs"));
+        assert!(!is_match("*.rs", b"lib.rs.bak"));
+        assert!(is_match("*a*b*", b"xaybz"));
+        assert!(!is_match("*a*b", b"xaybz"));
+        assert!(is_match("?.txt", b"a.txt"));
+        assert!(!is_match("?.txt", b"ab.txt"));
+        assert!(is_match("*/target/*", b"./target/debug"));
+        assert!(is_match("", b""));
+        assert!(!is_match("", b"a"));
+    }
+
+    #[test]
+    fn test_classes() {
+        assert!(is_match("[ab].txt", b"a.txt"));
+        assert!(!is_match("[ab].txt", b"c.txt"));
+        assert!(is_match("[!ab].txt", b"c.txt"));
+        assert!(is_match("[^a-c]", b"d"));
+        assert!(!is_match("[^a-c]", b"b"));
+        assert!(is_match("[]]", b"]"));
+        assert!(is_match("[a-]", b"-"));
+        assert!(is_match("[", b"["));
+    }
+
+    #[test]
+    fn test_escapes() {
+        assert!(is_match("\\*", b"*"));
+        assert!(!is_match("\\*", b"a"));
+        assert!(is_match("[\\]]", b"]"));
+    }
+
+    #[test]
+    fn test_non_utf8() {
+        assert!(is_match("bad?name", b"bad\xffname"));
+        assert!(is_match("*name", b"bad\xffname"));
+        assert!(is_match("caf?", "café".as_bytes()));
+        assert!(!is_match("[!a]", b"\xff\xfe"));
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        assert!(Glob::new("*.JPG", true).is_match(b"photo.jpg"));
+        assert!(Glob::new("*.jpg", true).is_match(b"PHOTO.JPG"));
+        assert!(!Glob::new("*.jpg", false).is_match(b"PHOTO.JPG"));
+    }
+}
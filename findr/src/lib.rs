@@ -1,27 +1,46 @@
-use std::ffi::OsStr;
 use std::fs;
-use std::os::unix::prelude::OsStrExt;
+use std::os::unix::fs::FileTypeExt;
 
 use clap::builder::PossibleValue;
-use clap::ArgAction;
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{ArgAction, CommandFactory, Parser};
 use regex::bytes::Regex;
-use walkdir::WalkDir;
 
+use crate::errors::Errors;
+use crate::expr::Expr;
+use crate::walk::{Follow, Walk};
 use crate::EntryType::*;
 
+mod contents;
+mod duplicates;
+mod errors;
+mod exec;
+mod expr;
+mod gitignore;
+mod glob;
+mod listing;
+mod printf;
+mod stat;
+mod walk;
+#[cfg(target_os = "linux")]
+mod watch;
+
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
 #[derive(Debug, PartialEq, Eq, Copy, Clone, PartialOrd, Ord)]
-enum EntryType {
+pub(crate) enum EntryType {
     Dir,
     File,
     Link,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
 }
 
 impl clap::ValueEnum for EntryType {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Dir, File, Link]
+        &[Dir, File, Link, Fifo, Socket, BlockDevice, CharDevice]
     }
 
     fn to_possible_value(&self) -> Option<PossibleValue> {
@@ -29,14 +48,77 @@ impl clap::ValueEnum for EntryType {
             Dir => PossibleValue::new("d").help("Match directories"),
             File => PossibleValue::new("f").help("Match files"),
             Link => PossibleValue::new("l").help("Match symlinks"),
+            Fifo => PossibleValue::new("p").help("Match named pipes"),
+            Socket => PossibleValue::new("s").help("Match sockets"),
+            BlockDevice => PossibleValue::new("b").help("Match block devices"),
+            CharDevice => PossibleValue::new("c").help("Match character devices"),
         })
     }
 }
 
+impl EntryType {
+    #[inline]
+    fn matches(&self, file_type: fs::FileType) -> bool {
+        match self {
+            Dir => file_type.is_dir(),
+            File => file_type.is_file(),
+            Link => file_type.is_symlink(),
+            Fifo => file_type.is_fifo(),
+            Socket => file_type.is_socket(),
+            BlockDevice => file_type.is_block_device(),
+            CharDevice => file_type.is_char_device(),
+        }
+    }
+
+    /// The type of a file, if it is one of those findr can test for.
+    fn of(file_type: fs::FileType) -> Option<EntryType> {
+        <EntryType as clap::ValueEnum>::value_variants()
+            .iter()
+            .copied()
+            .find(|entry_type| entry_type.matches(file_type))
+    }
+
+    /// The letter that stands for the type in --type, -type and -printf's %y.
+    fn letter(self) -> char {
+        match self {
+            Dir => 'd',
+            File => 'f',
+            Link => 'l',
+            Fifo => 'p',
+            Socket => 's',
+            BlockDevice => 'b',
+            CharDevice => 'c',
+        }
+    }
+}
+
 #[derive(Debug, clap::Parser)]
 #[command(version, about, author)]
 /// findr recursively descends the directory tree for each path listed, evaluating the provided
 /// expressions in terms of each file in the tree.
+///
+/// Expressions are built from the primaries -name GLOB, -iname GLOB, -path GLOB, -ipath GLOB,
+/// -regex REGEX, -iregex REGEX, -type TYPE[,TYPE...], -xtype TYPE[,TYPE...], -size [+-]N[bcwkMG],
+/// -atime/-ctime/-mtime [+-]DAYS, -newer FILE, -perm [-/]OCTAL, -user USER, -group GROUP, -empty,
+/// -inum [+-]N and -contains REGEX, combined with ! (or -not), -a (or -and), -o (or -or) and
+/// parentheses. Adjacent primaries are implicitly ANDed, and -a binds more tightly than -o. Globs
+/// support *, ? and [...]; regexes must match the whole path. -contains is true for text files with
+/// a line matching REGEX, and is evaluated after any cheaper tests it is ANDed with.
+///
+/// The actions -print, -print0, -printf FORMAT, -ls, -prune and -exec COMMAND ; are true if the
+/// action succeeds. -print0 terminates each path with a NUL byte rather than a newline, for use
+/// with xargs -0. -ls prints a long listing like that of ls -dils. -printf supports the directives
+/// %p (path), %f (file name), %h (leading directories), %P (path below the starting point), %H
+/// (starting point), %d (depth), %s (size in bytes), %y (type), %m (octal permissions) and %%, and
+/// the escapes \n, \t, \0 and \\. -exec replaces each {} in COMMAND with the current path, and as
+/// with find, is false if COMMAND fails without affecting findr's exit status, so that it can be
+/// used as a test; -exec COMMAND {} + instead runs COMMAND with as many paths as possible
+/// appended, and causes findr to exit unsuccessfully if any invocation fails. -quit stops findr at once, without evaluating the
+/// rest of the expression, so -print -quit prints only the first match. -prune stops findr from
+/// descending into the current directory. -delete removes the current entry, and directories only
+/// once their contents have been visited; entries that would be reached outside a starting path,
+/// such as through a link with -L, are never removed. Preview what -delete would do with --dry-run.
+/// If the expression contains no action other than -prune, matching paths are printed.
 pub struct Args {
     #[arg(value_name = "PATH", default_value = ".")]
     paths: Vec<String>,
@@ -58,63 +140,178 @@ pub struct Args {
         short = 't',
         action = ArgAction::Append,
         num_args = 0..,
-        default_values(vec!["d", "f", "l"]),
-        default_missing_values(vec!["d", "f", "l"]),
+        default_values(vec!["d", "f", "l", "p", "s", "b", "c"]),
+        default_missing_values(vec!["d", "f", "l", "p", "s", "b", "c"]),
     )]
     /// True if the file is of the specified type.
     entry_types: Vec<EntryType>,
+
+    /// Don't evaluate entries less than DEPTH levels below the starting paths.
+    #[arg(value_name = "DEPTH", long = "min-depth")]
+    min_depth: Option<usize>,
+
+    /// Descend at most DEPTH levels below the starting paths.
+    #[arg(value_name = "DEPTH", long = "max-depth")]
+    max_depth: Option<usize>,
+
+    /// Never follow symlinks, examining the links themselves. This is the default.
+    #[arg(short = 'P', overrides_with_all = ["follow_roots", "follow_all"])]
+    follow_none: bool,
+
+    /// Follow symlinks given as starting paths, but not those found below them.
+    #[arg(short = 'H', overrides_with_all = ["follow_none", "follow_all"])]
+    follow_roots: bool,
+
+    /// Follow all symlinks, examining the files they point to rather than the links. Broken links
+    /// are examined as links, and links to their own ancestors are reported as loops.
+    #[arg(short = 'L', overrides_with_all = ["follow_none", "follow_roots"])]
+    follow_all: bool,
+
+    /// Don't descend into directories on a different filesystem from their starting path. The
+    /// directories themselves are still evaluated. May also be given as -xdev or -mount.
+    #[arg(long = "one-file-system", short = 'x')]
+    one_file_system: bool,
+
+    /// Print each match as a JSON object on a line of its own, with its path, type, size in bytes,
+    /// modification time in seconds since the epoch, octal permissions and depth, rather than
+    /// printing its path.
+    #[arg(long = "json")]
+    json: bool,
+
+    /// Rather than printing matches, compare the regular files among them and print each group of
+    /// files with identical contents, one path per line, separating groups with a blank line. With
+    /// --json, print each group as an object with the size of the files and an array of their
+    /// paths. Empty files are left out, and hard links to the same file count as one.
+    #[arg(long = "duplicates")]
+    duplicates: bool,
+
+    /// Once the walk is over, keep running and visit entries as they are created in or moved into
+    /// the directories it read, printing any new matches. Runs until interrupted, or ended by -quit
+    /// or --max-results. Batches of -exec ... + run after each burst of changes. Linux only.
+    #[arg(long = "watch", conflicts_with = "duplicates")]
+    watch: bool,
+
+    /// Have -delete print each entry it would remove, in the order it would remove them, instead of
+    /// removing anything.
+    #[arg(long = "dry-run")]
+    dry_run: bool,
+
+    /// Skip entries excluded by .gitignore and .ignore files, read from each directory as it is
+    /// entered and from the ancestors of the starting paths within their git repositories, and by
+    /// the global git excludes file, as well as .git itself.
+    #[arg(long = "ignore")]
+    ignore: bool,
+
+    /// Report each entry skipped by --ignore on stderr, with the rule that excluded it.
+    #[arg(long = "explain-ignored", requires = "ignore")]
+    explain_ignored: bool,
+
+    /// Walk the tree with N threads. Output is in no particular order unless --sort is given.
+    #[arg(
+        value_name = "N",
+        long = "threads",
+        short = 'j',
+        default_value_t = 1,
+        value_parser = parse_positive
+    )]
+    threads: usize,
+
+    /// Print entries in a deterministic order, sorting the entries of each directory by name. Each
+    /// directory is printed before its contents and the starting paths in the order given. With
+    /// --threads, output is held until the walk is complete.
+    #[arg(long = "sort")]
+    sort: bool,
+
+    /// Stop after an action other than -prune or -quit has run for N entries, across all starting
+    /// paths, and exit unsuccessfully if one ran for none. With --threads, which N are found is
+    /// unpredictable.
+    #[arg(value_name = "N", long = "max-results", value_parser = parse_positive)]
+    max_results: Option<usize>,
+
+    /// Don't print errors such as unreadable directories, though they still cause findr to exit
+    /// unsuccessfully.
+    #[arg(long = "quiet-errors")]
+    quiet_errors: bool,
+
+    /// The --type and --name options combined with the find-style expression into a single tree.
+    #[arg(skip)]
+    expr: Option<Expr>,
 }
 
 impl Args {
     pub fn load() -> Self {
-        let mut args = Self::parse();
+        let (clap_args, expr_tokens) = expr::partition(std::env::args_os());
+        let mut args = Self::parse_from(clap_args);
         args.entry_types.sort();
         args.entry_types.dedup();
+
+        let expr = Expr::parse(expr_tokens)
+            .unwrap_or_else(|e| Self::command().error(ErrorKind::InvalidValue, e).exit());
+        if cfg!(not(target_os = "linux")) && args.watch {
+            Self::command()
+                .error(
+                    ErrorKind::InvalidValue,
+                    "--watch is only supported on Linux",
+                )
+                .exit();
+        }
+        if args.watch && expr.as_ref().is_some_and(Expr::deletes) {
+            let message = "-delete can't be used with --watch";
+            Self::command()
+                .error(ErrorKind::ArgumentConflict, message)
+                .exit();
+        }
+        let expr = Expr::with_default_action(Expr::and(args.options_as_expr(), expr)).optimize();
+        args.expr = Some(if args.duplicates {
+            expr.replace_print(|| Expr::Collect)
+        } else if args.json {
+            expr.replace_print(|| Expr::Json)
+        } else {
+            expr
+        });
         args
     }
 
-    fn as_filter(&self) -> impl Fn(&walkdir::DirEntry) -> bool + '_ {
-        |entry| {
-            file_type_matches(&self.entry_types, entry.file_type())
-                && file_name_matches(&self.names, entry.file_name())
+    /// The symlink mode chosen by -P, -H or -L, of which the last given wins.
+    fn follow(&self) -> Follow {
+        if self.follow_all {
+            Follow::Always
+        } else if self.follow_roots {
+            Follow::Roots
+        } else {
+            Follow::Never
         }
     }
-}
-
-#[inline]
-fn file_type_matches(targets: &[EntryType], entry_type: fs::FileType) -> bool {
-    targets.iter().any(|target| match target {
-        Dir => entry_type.is_dir(),
-        File => entry_type.is_file(),
-        Link => entry_type.is_symlink(),
-    })
-}
 
-#[inline]
-fn file_name_matches(patterns: &[Regex], name: &OsStr) -> bool {
-    patterns.is_empty()
-        || patterns
-            .iter()
-            .any(|pattern| pattern.is_match(name.as_bytes()))
+    /// Express the --type and --name options as a predicate tree: the entry must be of one of the
+    /// given types AND, if any names were given, match one of them.
+    fn options_as_expr(&self) -> Option<Expr> {
+        let types = Some(Expr::Type(self.entry_types.clone()));
+        let names = Expr::any(self.names.iter().cloned().map(Expr::NameRegex));
+        Expr::and(types, names)
+    }
 }
 
-pub fn run(args: Args) -> Result<()> {
-    for path in &args.paths {
-        walk_path(&args, path)
+fn parse_positive(arg: &str) -> std::result::Result<usize, String> {
+    match arg.parse() {
+        Ok(0) => Err("must be at least 1".to_string()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(e.to_string()),
     }
-
-    Ok(())
 }
 
-fn walk_path(args: &Args, path: &String) {
-    WalkDir::new(path)
-        .into_iter()
-        .filter_map(|result| {
-            if let Err(e) = &result {
-                eprintln!("{}", e);
-            };
-            result.ok()
-        })
-        .filter(args.as_filter())
-        .for_each(|entry| println!("{}", entry.path().display()));
+/// Run findr, returning false if it failed without an error left to report: that is, if errors
+/// were reported along the way, or --max-results was given but nothing was found.
+pub fn run(args: Args) -> Result<bool> {
+    let print = Expr::Print;
+    let expr = args.expr.as_ref().unwrap_or(&print);
+    let errors = Errors::new(args.quiet_errors);
+    let results = Walk::new(&args, expr, &errors).run();
+    if results.is_ok() {
+        // Run the last of any batched `-exec ... +` commands.
+        expr.flush(&errors);
+    }
+    errors.summarize();
+    let results = results?;
+    Ok(errors.count() == 0 && (args.max_results.is_none() || results > 0))
 }
@@ -0,0 +1,137 @@
+//! Descriptions of entries beyond their paths: the long listing printed by -ls, and the JSON
+//! objects printed by -print with --json.
+
+use std::fs;
+use std::fs::Metadata;
+use std::io;
+use std::io::Write;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::prelude::OsStrExt;
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Local};
+use serde_json::json;
+
+use crate::errors::Errors;
+use crate::stat::TimeField;
+use crate::walk::Entry;
+use crate::EntryType;
+
+/// Files modified longer ago than this, or in the future, are listed with the year rather than the
+/// time of day, as `ls -l` does.
+const RECENT: Duration = Duration::from_secs(60 * 60 * 24 * 365 / 2);
+
+/// Write a line in the format of `find -ls`: inode, size in KiB blocks, mode, link count, owner,
+/// group, size in bytes, modification time and path, followed by the target of a link.
+pub(crate) fn write_ls(out: &mut impl Write, entry: &Entry) -> io::Result<()> {
+    let metadata = entry.metadata()?;
+    let user = uzers::get_user_by_uid(metadata.uid()).map_or_else(
+        || metadata.uid().to_string(),
+        |user| user.name().to_string_lossy().into(),
+    );
+    let group = uzers::get_group_by_gid(metadata.gid()).map_or_else(
+        || metadata.gid().to_string(),
+        |group| group.name().to_string_lossy().into(),
+    );
+
+    write!(
+        out,
+        "{:>9} {:>6} {} {:>3} {:<8} {:<8} {:>8} {} ",
+        metadata.ino(),
+        metadata.blocks().div_ceil(2),
+        mode_string(metadata),
+        metadata.nlink(),
+        user,
+        group,
+        metadata.len(),
+        listing_time(TimeField::Modified.of(metadata), SystemTime::now()),
+    )?;
+    out.write_all(entry.path().as_os_str().as_bytes())?;
+    if metadata.file_type().is_symlink() {
+        if let Ok(target) = fs::read_link(entry.path()) {
+            out.write_all(b" -> ")?;
+            out.write_all(target.as_os_str().as_bytes())?;
+        }
+    }
+    out.write_all(b"\n")
+}
+
+/// Write a JSON object describing the entry on a line of its own. Fields that depend on metadata
+/// are null if it can't be read, which is reported. Paths that aren't valid UTF-8 are converted
+/// lossily.
+pub(crate) fn write_json(out: &mut impl Write, entry: &Entry, errors: &Errors) -> io::Result<()> {
+    let metadata = entry
+        .metadata()
+        .inspect_err(|e| errors.report(format_args!("{}: {}", entry.path().display(), e)))
+        .ok();
+    let object = json!({
+        "path": entry.path().to_string_lossy(),
+        "type": EntryType::of(entry.file_type()).map(EntryType::letter),
+        "size": metadata.map(Metadata::len),
+        "mtime": metadata.map(Metadata::mtime),
+        "mode": metadata.map(|metadata| format!("{:o}", metadata.mode() & 0o7777)),
+        "depth": entry.depth(),
+    });
+    serde_json::to_writer(&mut *out, &object)?;
+    out.write_all(b"\n")
+}
+
+/// The file type and permissions in the symbolic form used by `ls -l`, such as `drwxr-xr-x`.
+fn mode_string(metadata: &Metadata) -> String {
+    let kind = match EntryType::of(metadata.file_type()) {
+        Some(EntryType::File) | None => '-',
+        Some(entry_type) => entry_type.letter(),
+    };
+
+    let mut string = String::from(kind);
+    string.push_str(&permissions_string(metadata.mode()));
+    string
+}
+
+/// The permission bits of `mode` in symbolic form, such as `rwsr-xr-x`.
+fn permissions_string(mode: u32) -> String {
+    let mut string = String::new();
+    // Each class of user, with its execute bit's special meaning when set with the given bit.
+    for (shift, special, set, unset) in [
+        (6, 0o4000, 's', 'S'),
+        (3, 0o2000, 's', 'S'),
+        (0, 0o1000, 't', 'T'),
+    ] {
+        let bits = (mode >> shift) & 0o7;
+        string.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        string.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        string.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    string
+}
+
+/// Format a modification time as `ls -l` does: with the time of day if it is recent, and the year
+/// otherwise.
+fn listing_time(time: SystemTime, now: SystemTime) -> String {
+    let recent = now
+        .duration_since(time)
+        .is_ok_and(|elapsed| elapsed < RECENT);
+    let format = if recent { "%b %e %H:%M" } else { "%b %e  %Y" };
+    DateTime::<Local>::from(time).format(format).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{mode_string, permissions_string};
+
+    #[test]
+    fn test_mode_string() {
+        assert_eq!(permissions_string(0o644), "rw-r--r--");
+        assert_eq!(permissions_string(0o4755), "rwsr-xr-x");
+        assert_eq!(permissions_string(0o2640), "rw-r-S---");
+        assert_eq!(permissions_string(0o1777), "rwxrwxrwt");
+        assert!(mode_string(&fs::symlink_metadata("/").unwrap()).starts_with('d'));
+    }
+}
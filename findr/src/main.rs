@@ -1,6 +1,10 @@
 fn main() {
-    if let Err(e) = findr::run(findr::Args::load()) {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match findr::run(findr::Args::load()) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
     }
 }
@@ -0,0 +1,167 @@
+//! The format language of the -printf action, a subset of GNU find's.
+
+use std::io;
+use std::io::Write;
+use std::mem;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::prelude::OsStrExt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::walk::Entry;
+use crate::{EntryType, Result};
+
+#[derive(Debug, PartialEq, Eq)]
+enum Directive {
+    Literal(Vec<u8>),
+    /// `%p`: the path.
+    Path,
+    /// `%f`: the file name, with any leading directories removed.
+    Name,
+    /// `%h`: the leading directories of the path, or `.` if there are none.
+    Dir,
+    /// `%P`: the path with the starting point removed.
+    RelativePath,
+    /// `%H`: the starting point under which the file was found.
+    StartingPoint,
+    /// `%d`: the depth of the file below the starting point.
+    Depth,
+    /// `%s`: the size of the file in bytes.
+    Size,
+    /// `%y`: the type of the file, as a -type letter.
+    Type,
+    /// `%m`: the permission bits of the file, in octal.
+    Mode,
+}
+
+#[derive(Debug)]
+pub(crate) struct Format(Vec<Directive>);
+
+impl Format {
+    /// Parse a format string, in which `%` introduces a directive and `\` an escape sequence.
+    /// Unlike -print, no newline is added.
+    pub(crate) fn parse(format: &str) -> Result<Format> {
+        let mut directives = Vec::new();
+        let mut literal = Vec::new();
+        let mut chars = format.chars();
+
+        while let Some(c) = chars.next() {
+            let directive = match c {
+                '%' => match chars.next() {
+                    Some('%') => {
+                        literal.push(b'%');
+                        continue;
+                    }
+                    Some('p') => Directive::Path,
+                    Some('f') => Directive::Name,
+                    Some('h') => Directive::Dir,
+                    Some('P') => Directive::RelativePath,
+                    Some('H') => Directive::StartingPoint,
+                    Some('d') => Directive::Depth,
+                    Some('s') => Directive::Size,
+                    Some('y') => Directive::Type,
+                    Some('m') => Directive::Mode,
+                    Some(other) => {
+                        return Err(format!("unknown -printf directive '%{}'", other).into())
+                    }
+                    None => return Err("-printf format ends with a lone '%'".into()),
+                },
+                '\\' => {
+                    literal.push(match chars.next() {
+                        Some('n') => b'\n',
+                        Some('t') => b'\t',
+                        Some('0') => b'\0',
+                        Some('\\') => b'\\',
+                        Some(other) => {
+                            return Err(format!("unknown -printf escape '\\{}'", other).into())
+                        }
+                        None => return Err("-printf format ends with a lone '\\'".into()),
+                    });
+                    continue;
+                }
+                _ => {
+                    let mut buf = [0; 4];
+                    literal.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    continue;
+                }
+            };
+
+            if !literal.is_empty() {
+                directives.push(Directive::Literal(mem::take(&mut literal)));
+            }
+            directives.push(directive);
+        }
+
+        if !literal.is_empty() {
+            directives.push(Directive::Literal(literal));
+        }
+        Ok(Format(directives))
+    }
+
+    pub(crate) fn write(&self, out: &mut impl Write, entry: &Entry) -> io::Result<()> {
+        let path = entry.path();
+        for directive in &self.0 {
+            match directive {
+                Directive::Literal(bytes) => out.write_all(bytes)?,
+                Directive::Path => out.write_all(path.as_os_str().as_bytes())?,
+                Directive::Name => out.write_all(entry.file_name().as_bytes())?,
+                Directive::Dir => match path.parent() {
+                    Some(parent) if parent != Path::new("") => {
+                        out.write_all(parent.as_os_str().as_bytes())?
+                    }
+                    _ => out.write_all(b".")?,
+                },
+                Directive::RelativePath => {
+                    let components = path.components().collect::<Vec<_>>();
+                    let relative = &components[components.len() - entry.depth()..];
+                    write_components(out, relative)?
+                }
+                Directive::StartingPoint => {
+                    let components = path.components().collect::<Vec<_>>();
+                    let root = &components[..components.len() - entry.depth()];
+                    write_components(out, root)?
+                }
+                Directive::Depth => write!(out, "{}", entry.depth())?,
+                Directive::Size => write!(out, "{}", entry.metadata()?.len())?,
+                Directive::Type => {
+                    let letter = EntryType::of(entry.file_type()).map_or('U', EntryType::letter);
+                    write!(out, "{}", letter)?
+                }
+                Directive::Mode => {
+                    write!(out, "{:o}", entry.metadata()?.permissions().mode() & 0o7777)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_components(out: &mut impl Write, components: &[Component]) -> io::Result<()> {
+    let path = components.iter().collect::<PathBuf>();
+    out.write_all(path.as_os_str().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Directive, Format};
+
+    #[test]
+    fn test_parse() {
+        let format = Format::parse("%p\\t%s%%\\0").unwrap();
+        assert_eq!(
+            format.0,
+            vec![
+                Directive::Path,
+                Directive::Literal(b"\t".to_vec()),
+                Directive::Size,
+                Directive::Literal(b"%\0".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for format in ["%", "%q", "\\", "\\q"] {
+            assert!(Format::parse(format).is_err(), "{}", format);
+        }
+    }
+}
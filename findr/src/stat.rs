@@ -0,0 +1,124 @@
+//! Helpers for the primaries that test file metadata: timestamps, permissions and ownership.
+
+use std::fs;
+use std::fs::Metadata;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::Result;
+
+/// The permission bits compared by -perm, including the setuid, setgid and sticky bits.
+const PERMISSION_BITS: u32 = 0o7777;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum TimeField {
+    Accessed,
+    /// The last change to the file's inode, such as a rename or chmod, as well as its contents.
+    Changed,
+    Modified,
+}
+
+impl TimeField {
+    pub(crate) fn of(self, metadata: &Metadata) -> SystemTime {
+        let (secs, nsecs) = match self {
+            TimeField::Accessed => (metadata.atime(), metadata.atime_nsec()),
+            TimeField::Changed => (metadata.ctime(), metadata.ctime_nsec()),
+            TimeField::Modified => (metadata.mtime(), metadata.mtime_nsec()),
+        };
+        let whole_secs = Duration::from_secs(secs.unsigned_abs());
+        let time = if secs < 0 {
+            SystemTime::UNIX_EPOCH - whole_secs
+        } else {
+            SystemTime::UNIX_EPOCH + whole_secs
+        };
+        time + Duration::from_nanos(nsecs as u64)
+    }
+}
+
+/// A -perm argument: `MODE` for exactly these bits, `-MODE` for at least all of these bits, or
+/// `/MODE` for any of these bits. Modes are octal.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum Perm {
+    Exactly(u32),
+    AllOf(u32),
+    AnyOf(u32),
+}
+
+impl Perm {
+    pub(crate) fn parse(arg: &str) -> Result<Perm> {
+        let (perm, mode): (fn(u32) -> Perm, &str) = if let Some(mode) = arg.strip_prefix('-') {
+            (Perm::AllOf, mode)
+        } else if let Some(mode) = arg.strip_prefix('/') {
+            (Perm::AnyOf, mode)
+        } else {
+            (Perm::Exactly, arg)
+        };
+
+        match u32::from_str_radix(mode, 8) {
+            Ok(mode) if mode <= PERMISSION_BITS => Ok(perm(mode)),
+            _ => Err(format!("invalid mode '{}'", arg).into()),
+        }
+    }
+
+    pub(crate) fn matches(self, metadata: &Metadata) -> bool {
+        let mode = metadata.permissions().mode() & PERMISSION_BITS;
+        match self {
+            Perm::Exactly(bits) => mode == bits,
+            Perm::AllOf(bits) => mode & bits == bits,
+            // As in GNU find, `/000` matches every file.
+            Perm::AnyOf(bits) => bits == 0 || mode & bits != 0,
+        }
+    }
+}
+
+/// Resolve a -user argument, which may be a user name or a numeric ID.
+pub(crate) fn parse_uid(arg: &str) -> Result<u32> {
+    match uzers::get_user_by_name(arg) {
+        Some(user) => Ok(user.uid()),
+        None => arg
+            .parse()
+            .map_err(|_| format!("'{}' is not the name of a known user", arg).into()),
+    }
+}
+
+/// Resolve a -group argument, which may be a group name or a numeric ID.
+pub(crate) fn parse_gid(arg: &str) -> Result<u32> {
+    match uzers::get_group_by_name(arg) {
+        Some(group) => Ok(group.gid()),
+        None => arg
+            .parse()
+            .map_err(|_| format!("'{}' is not the name of an existing group", arg).into()),
+    }
+}
+
+/// The modification time of the reference file given to -newer.
+pub(crate) fn reference_time(path: &str) -> Result<SystemTime> {
+    let metadata = fs::metadata(path).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(TimeField::Modified.of(&metadata))
+}
+
+/// True if `path` is an empty regular file or a directory with no entries.
+pub(crate) fn is_empty(path: &Path, metadata: &Metadata) -> bool {
+    if metadata.is_file() {
+        metadata.len() == 0
+    } else if metadata.is_dir() {
+        fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Perm;
+
+    #[test]
+    fn test_parse_perm() {
+        assert_eq!(Perm::parse("644").unwrap(), Perm::Exactly(0o644));
+        assert_eq!(Perm::parse("-111").unwrap(), Perm::AllOf(0o111));
+        assert_eq!(Perm::parse("/4000").unwrap(), Perm::AnyOf(0o4000));
+        assert!(Perm::parse("u+x").is_err());
+        assert!(Perm::parse("17777").is_err());
+    }
+}
@@ -0,0 +1,792 @@
+//! Traversal of the trees below the starting paths.
+//!
+//! With a single thread, each tree is walked in turn with walkdir. With more, directories are
+//! shared among a pool of workers, each of which reads directories from its own queue and steals
+//! from the others once its queue runs dry. Either way, every entry is passed to [Walk::visit],
+//! which evaluates the expression and decides whether to descend.
+//!
+//! When symlinks are followed, an entry reached through a link takes on the type and metadata of
+//! its target, while a broken link is examined as a link. A link to one of its own ancestors would
+//! send the walk round in circles, so it is reported as an error and not descended into.
+//!
+//! Entries matched by -delete are removed as they are visited, except for directories, which are
+//! removed once the walk is over, deepest first, so that their contents go before them. Nothing is
+//! removed unless it lies within its starting path once links are resolved, which guards against
+//! following a link out of the tree with -L.
+//!
+//! With --watch, every directory is read by the pool, even with a single thread, so that each can
+//! be watched before it is read. Once the walk is over, entries that appear in those directories
+//! are visited as they arrive, and any new directories are walked in turn.
+
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::ffi::OsStr;
+use std::fs::{FileType, Metadata};
+use std::io::Write;
+use std::mem;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::prelude::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::{fs, io, iter, thread};
+
+use crossbeam_deque::{Injector, Steal, Stealer, Worker};
+use walkdir::WalkDir;
+
+use crate::duplicates::{self, Candidate};
+use crate::errors::Errors;
+use crate::expr::{Context, Expr};
+use crate::gitignore::Ignores;
+#[cfg(target_os = "linux")]
+use crate::watch::Watcher;
+use crate::{Args, Result};
+
+/// Which symlinks the walk follows, as chosen by -P, -H and -L.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub(crate) enum Follow {
+    /// Examine links themselves, and never descend through them.
+    Never,
+    /// Follow links given as starting paths, but not those found below them.
+    Roots,
+    /// Follow every link.
+    Always,
+}
+
+/// A file found by the walk.
+#[derive(Debug)]
+pub(crate) struct Entry {
+    path: PathBuf,
+    /// The type of the file, or of its target if it is a link that was followed.
+    file_type: FileType,
+    /// Set if the path is a link that was followed.
+    followed: bool,
+    depth: usize,
+    /// Cached on first use, since many primaries consult the metadata.
+    metadata: OnceCell<io::Result<Metadata>>,
+}
+
+impl Entry {
+    fn new(path: PathBuf, file_type: FileType, depth: usize) -> Entry {
+        Entry {
+            path,
+            file_type,
+            followed: false,
+            depth,
+            metadata: OnceCell::new(),
+        }
+    }
+
+    fn from_walkdir(entry: walkdir::DirEntry) -> Entry {
+        let (file_type, depth) = (entry.file_type(), entry.depth());
+        let followed = entry.path_is_symlink() && !file_type.is_symlink();
+        Entry {
+            followed,
+            ..Entry::new(entry.into_path(), file_type, depth)
+        }
+    }
+
+    /// Create the entry for a starting path.
+    fn from_root(path: &str) -> io::Result<Entry> {
+        let metadata = fs::symlink_metadata(path)?;
+        let entry = Entry::new(PathBuf::from(path), metadata.file_type(), 0);
+        let _ = entry.metadata.set(Ok(metadata));
+        Ok(entry)
+    }
+
+    /// Create the entry for a broken link, which walkdir reports as an error when following links.
+    fn from_broken_link(e: &walkdir::Error) -> Option<Entry> {
+        let path = e.path()?;
+        let metadata = fs::symlink_metadata(path).ok()?;
+        if !metadata.file_type().is_symlink() || fs::metadata(path).is_ok() {
+            return None;
+        }
+        let entry = Entry::new(path.to_path_buf(), metadata.file_type(), e.depth());
+        let _ = entry.metadata.set(Ok(metadata));
+        Some(entry)
+    }
+
+    /// Follow the entry if it is a link, unless the link is broken.
+    fn follow(self) -> Entry {
+        if !self.file_type.is_symlink() {
+            return self;
+        }
+        match fs::metadata(&self.path) {
+            Ok(metadata) => Entry {
+                file_type: metadata.file_type(),
+                followed: true,
+                metadata: OnceCell::from(Ok(metadata)),
+                ..self
+            },
+            Err(_) => self,
+        }
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The final component of the path, or the whole path if it has none, such as `.`.
+    pub(crate) fn file_name(&self) -> &OsStr {
+        self.path
+            .file_name()
+            .unwrap_or_else(|| self.path.as_os_str())
+    }
+
+    pub(crate) fn file_type(&self) -> FileType {
+        self.file_type
+    }
+
+    /// The type of the file as seen from the other side of a link, as tested by -xtype: the type
+    /// of the target of an unfollowed link, or of the link itself if it was followed. Other
+    /// entries, including broken links, have their own type.
+    pub(crate) fn xtype(&self) -> FileType {
+        let metadata = if self.followed {
+            fs::symlink_metadata(&self.path)
+        } else if self.file_type.is_symlink() {
+            fs::metadata(&self.path)
+        } else {
+            return self.file_type;
+        };
+        metadata.map_or(self.file_type, |metadata| metadata.file_type())
+    }
+
+    /// The number of levels below its starting path at which the entry was found.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The entry's metadata, which is that of the target if the entry is a link that was
+    /// followed.
+    pub(crate) fn metadata(&self) -> io::Result<&Metadata> {
+        self.metadata
+            .get_or_init(|| {
+                if self.followed {
+                    fs::metadata(&self.path)
+                } else {
+                    fs::symlink_metadata(&self.path)
+                }
+            })
+            .as_ref()
+            .map_err(|e| match e.raw_os_error() {
+                Some(code) => io::Error::from_raw_os_error(code),
+                None => io::Error::new(e.kind(), e.to_string()),
+            })
+    }
+}
+
+/// What the walk should do after visiting an entry.
+enum Visit {
+    /// Descend into the entry, if it is a directory, under the given ignore rules.
+    Descend(Option<Ignores>),
+    /// Don't descend into the entry.
+    Skip,
+}
+
+/// A directory waiting to be read by the parallel walker.
+#[derive(Clone)]
+struct Job {
+    path: PathBuf,
+    depth: usize,
+    ignores: Option<Ignores>,
+    /// The directory itself and those above it, if links are being followed.
+    ancestors: Option<Arc<Ancestor>>,
+    /// The device of the starting path, if the walk must stay on its filesystem.
+    device: Option<u64>,
+}
+
+/// A directory on the way down from a starting path, used to detect loops through links.
+struct Ancestor {
+    path: PathBuf,
+    /// The device and inode numbers of the directory.
+    id: (u64, u64),
+    parent: Option<Arc<Ancestor>>,
+}
+
+impl Ancestor {
+    /// Extend the chain of `ancestors` with the directory `entry`.
+    fn push(ancestors: Option<Arc<Ancestor>>, entry: &Entry) -> Option<Arc<Ancestor>> {
+        match entry.metadata() {
+            Ok(metadata) => Some(Arc::new(Ancestor {
+                path: entry.path().to_path_buf(),
+                id: (metadata.dev(), metadata.ino()),
+                parent: ancestors,
+            })),
+            Err(_) => ancestors,
+        }
+    }
+
+    /// Find the ancestor that `entry`, a directory reached through a link, points to, if any.
+    fn find<'a>(ancestors: Option<&'a Arc<Ancestor>>, entry: &Entry) -> Option<&'a Path> {
+        let metadata = entry.metadata().ok()?;
+        let id = (metadata.dev(), metadata.ino());
+        iter::successors(ancestors, |ancestor| ancestor.parent.as_ref())
+            .find(|ancestor| ancestor.id == id)
+            .map(|ancestor| ancestor.path.as_path())
+    }
+}
+
+/// Where parallel workers with nothing to do sleep until another queues a directory, rather than
+/// spinning while a slow read, such as one on a network mount, holds up the rest of the walk.
+#[derive(Default)]
+struct Idle {
+    /// Counts the wakeups so far, so that a worker doesn't sleep through one that came between
+    /// finding nothing to do and going to sleep.
+    wakeups: Mutex<u64>,
+    condvar: Condvar,
+}
+
+impl Idle {
+    fn wakeups(&self) -> u64 {
+        *self.wakeups.lock().unwrap()
+    }
+
+    /// Sleep until the next wakeup, unless there have been any since `wakeups` were counted.
+    fn sleep(&self, wakeups: u64) {
+        let guard = self.wakeups.lock().unwrap();
+        let _guard = self
+            .condvar
+            .wait_while(guard, |current| *current == wakeups)
+            .unwrap();
+    }
+
+    /// Wake a worker to take a newly queued directory.
+    fn wake_one(&self) {
+        *self.wakeups.lock().unwrap() += 1;
+        self.condvar.notify_one();
+    }
+
+    /// Wake every worker, since the walk is over.
+    fn wake_all(&self) {
+        *self.wakeups.lock().unwrap() += 1;
+        self.condvar.notify_all();
+    }
+}
+
+/// The output of an entry, keyed by the index of its starting path and its own path.
+type SortedOutput = ((usize, PathBuf), Vec<u8>);
+
+/// The state shared by every thread of a walk.
+pub(crate) struct Walk<'a> {
+    args: &'a Args,
+    expr: &'a Expr,
+    follow: Follow,
+    errors: &'a Errors,
+    /// Set when the walk must end early.
+    quit: AtomicBool,
+    /// The number of entries for which the expression was true.
+    results: AtomicUsize,
+    /// The first error writing output, which ends the walk.
+    error: Mutex<Option<io::Error>>,
+    /// The output held until the walk is complete, if it is to be sorted.
+    sorted_output: Option<Mutex<Vec<SortedOutput>>>,
+    /// The starting paths with all links resolved, or `None` for those that can't be resolved.
+    real_roots: Vec<Option<PathBuf>>,
+    /// Directories to be removed by -delete once the walk is over, with their depths.
+    doomed_dirs: Mutex<Vec<(usize, PathBuf)>>,
+    /// Files to be compared once the walk is over, with --duplicates.
+    candidates: Mutex<Vec<Candidate>>,
+    /// The directories read so far, with --watch.
+    #[cfg(target_os = "linux")]
+    watcher: Option<Watcher<(usize, Job)>>,
+    /// Parallel workers waiting for directories to read.
+    idle: Idle,
+}
+
+impl<'a> Walk<'a> {
+    pub(crate) fn new(args: &'a Args, expr: &'a Expr, errors: &'a Errors) -> Walk<'a> {
+        // A sequential walk sorts directory entries as it goes, but a parallel walk must defer
+        // the output of each entry until it can be sorted.
+        let sorted_output = (args.sort && (args.threads > 1 || args.watch)).then(Mutex::default);
+        let real_roots = args
+            .paths
+            .iter()
+            .map(|path| fs::canonicalize(path).ok())
+            .collect();
+        Walk {
+            args,
+            expr,
+            follow: args.follow(),
+            errors,
+            quit: AtomicBool::new(false),
+            results: AtomicUsize::new(0),
+            error: Mutex::new(None),
+            sorted_output,
+            real_roots,
+            doomed_dirs: Mutex::default(),
+            candidates: Mutex::default(),
+            #[cfg(target_os = "linux")]
+            watcher: None,
+            idle: Idle::default(),
+        }
+    }
+
+    /// Walk every starting path, returning the number of entries for which the expression was
+    /// true.
+    pub(crate) fn run(mut self) -> Result<usize> {
+        #[cfg(target_os = "linux")]
+        if self.args.watch {
+            self.watcher = Some(Watcher::new()?);
+        }
+        if self.args.threads > 1 || self.args.watch {
+            self.walk_parallel();
+        } else {
+            for (index, root) in self.args.paths.iter().enumerate() {
+                if self.quit.load(Ordering::Relaxed) {
+                    break;
+                }
+                self.walk_sequential(index, root);
+            }
+        }
+
+        // Anything visited from here on, while watching, is written out straight away.
+        if let Some(output) = self.sorted_output.take() {
+            let mut output = output.into_inner().unwrap();
+            output.sort_unstable_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+            let mut stdout = io::stdout().lock();
+            for (_, bytes) in output {
+                stdout.write_all(&bytes)?;
+            }
+        }
+
+        let mut doomed_dirs = mem::take(self.doomed_dirs.get_mut().unwrap());
+        doomed_dirs.sort_unstable_by(|(lhs_depth, lhs), (rhs_depth, rhs)| {
+            (Reverse(lhs_depth), lhs).cmp(&(Reverse(rhs_depth), rhs))
+        });
+        for (_, dir) in doomed_dirs {
+            if self.args.dry_run {
+                write_removal(&mut io::stdout().lock(), &dir)?;
+            } else if let Err(e) = remove(&dir) {
+                self.errors.report(format_args!("{}: {}", dir.display(), e));
+            }
+        }
+
+        if let Some(e) = self.error.get_mut().unwrap().take() {
+            return Err(e.into());
+        }
+        if self.args.duplicates {
+            let candidates = mem::take(self.candidates.get_mut().unwrap());
+            let groups = duplicates::find(candidates, self.errors);
+            duplicates::write(&mut io::stdout().lock(), &groups, self.args.json)?;
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(watcher) = &self.watcher {
+            self.watch(watcher)?;
+            if let Some(e) = self.error.get_mut().unwrap().take() {
+                return Err(e.into());
+            }
+        }
+        Ok(self.results.into_inner())
+    }
+
+    fn walk_sequential(&self, index: usize, root: &str) {
+        let mut walker = WalkDir::new(root);
+        if let Some(depth) = self.args.max_depth {
+            walker = walker.max_depth(depth);
+        }
+        if self.args.sort {
+            walker = walker.sort_by_file_name();
+        }
+        walker = walker
+            .follow_links(self.follow == Follow::Always)
+            .follow_root_links(self.follow != Follow::Never)
+            .same_file_system(self.args.one_file_system);
+
+        // The ignore rules for entries at each depth of the current branch.
+        let mut ignores: Vec<Ignores> = Vec::new();
+        if self.args.ignore {
+            ignores.push(Ignores::new(Path::new(root), self.errors));
+        }
+
+        let mut entries = walker.into_iter();
+        while let Some(result) = entries.next() {
+            let entry = match result {
+                // walkdir descends through a link given as a starting path, but examines the link.
+                Ok(entry) if entry.depth() == 0 && self.follow == Follow::Roots => {
+                    Entry::from_walkdir(entry).follow()
+                }
+                Ok(entry) => Entry::from_walkdir(entry),
+                Err(e) => match Entry::from_broken_link(&e) {
+                    Some(entry) => entry,
+                    None => {
+                        self.errors.report(e);
+                        continue;
+                    }
+                },
+            };
+
+            ignores.truncate(entry.depth() + 1);
+            match self.visit(index, &entry, ignores.get(entry.depth())) {
+                Visit::Descend(Some(descendant_ignores)) => ignores.push(descendant_ignores),
+                Visit::Descend(None) => {}
+                Visit::Skip if entry.file_type().is_dir() => entries.skip_current_dir(),
+                Visit::Skip => {}
+            }
+
+            if self.quit.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+    }
+
+    fn walk_parallel(&self) {
+        let injector = Injector::new();
+        let pending = AtomicUsize::new(0);
+
+        for (index, root) in self.args.paths.iter().enumerate() {
+            let entry = match Entry::from_root(root) {
+                Ok(entry) if self.follow == Follow::Never => entry,
+                Ok(entry) => entry.follow(),
+                Err(e) => {
+                    self.report(Path::new(root), &e);
+                    continue;
+                }
+            };
+
+            let ignores = self
+                .args
+                .ignore
+                .then(|| Ignores::new(Path::new(root), self.errors));
+            if let Visit::Descend(ignores) = self.visit(index, &entry, ignores.as_ref()) {
+                if entry.file_type().is_dir() && self.args.max_depth != Some(0) {
+                    let ancestors = match self.follow {
+                        Follow::Always => Ancestor::push(None, &entry),
+                        _ => None,
+                    };
+                    let device = match entry.metadata() {
+                        Ok(metadata) if self.args.one_file_system => Some(metadata.dev()),
+                        _ => None,
+                    };
+                    pending.fetch_add(1, Ordering::SeqCst);
+                    injector.push((
+                        index,
+                        Job {
+                            path: entry.path,
+                            depth: 0,
+                            ignores,
+                            ancestors,
+                            device,
+                        },
+                    ));
+                }
+            }
+        }
+
+        let workers: Vec<Worker<(usize, Job)>> = iter::repeat_with(Worker::new_lifo)
+            .take(self.args.threads)
+            .collect();
+        let stealers: Vec<Stealer<(usize, Job)>> = workers.iter().map(Worker::stealer).collect();
+        thread::scope(|scope| {
+            for worker in workers {
+                let (injector, stealers, pending) = (&injector, &stealers, &pending);
+                scope.spawn(move || self.work(&worker, injector, stealers, pending));
+            }
+        });
+    }
+
+    /// Read directories until there are none left, either queued or being read.
+    fn work(
+        &self,
+        local: &Worker<(usize, Job)>,
+        injector: &Injector<(usize, Job)>,
+        stealers: &[Stealer<(usize, Job)>],
+        pending: &AtomicUsize,
+    ) {
+        while !self.quit.load(Ordering::Relaxed) {
+            let wakeups = self.idle.wakeups();
+            match find_job(local, injector, stealers) {
+                Some((index, job)) => {
+                    #[cfg(target_os = "linux")]
+                    if let Some(watcher) = &self.watcher {
+                        if let Err(e) = watcher.add(&job.path, (index, job.clone())) {
+                            self.report(&job.path, &e);
+                        }
+                    }
+                    self.read_dir(index, job, local, pending);
+                    pending.fetch_sub(1, Ordering::SeqCst);
+                }
+                None if pending.load(Ordering::SeqCst) == 0 => break,
+                None => self.idle.sleep(wakeups),
+            }
+        }
+        // Whether the last directory has been read or the walk was ended early, the workers still
+        // asleep will find nothing more to do.
+        self.idle.wake_all();
+    }
+
+    fn read_dir(
+        &self,
+        index: usize,
+        job: Job,
+        local: &Worker<(usize, Job)>,
+        pending: &AtomicUsize,
+    ) {
+        let dir = match fs::read_dir(&job.path) {
+            Ok(dir) => dir,
+            Err(e) => return self.report(&job.path, &e),
+        };
+
+        for child in dir {
+            if self.quit.load(Ordering::Relaxed) {
+                return;
+            }
+
+            match child.and_then(|child| Ok((child.path(), child.file_type()?))) {
+                Ok((path, file_type)) => {
+                    self.visit_child(index, &job, path, file_type, local, pending)
+                }
+                Err(e) => self.report(&job.path, &e),
+            }
+        }
+    }
+
+    /// Visit an entry of the directory read by `job`, and queue it to be read in turn if the walk
+    /// is to descend into it.
+    fn visit_child(
+        &self,
+        index: usize,
+        job: &Job,
+        path: PathBuf,
+        file_type: FileType,
+        local: &Worker<(usize, Job)>,
+        pending: &AtomicUsize,
+    ) {
+        let mut entry = Entry::new(path, file_type, job.depth + 1);
+        if self.follow == Follow::Always {
+            entry = entry.follow();
+            if entry.followed && entry.file_type.is_dir() {
+                if let Some(ancestor) = Ancestor::find(job.ancestors.as_ref(), &entry) {
+                    self.errors.report(format_args!(
+                        "File system loop found: {} points to an ancestor {}",
+                        entry.path().display(),
+                        ancestor.display()
+                    ));
+                    return;
+                }
+            }
+        }
+
+        if let Visit::Descend(ignores) = self.visit(index, &entry, job.ignores.as_ref()) {
+            let below_max_depth = self.args.max_depth.is_none_or(|max| entry.depth < max);
+            let same_device = job.device.is_none_or(|device| {
+                entry
+                    .metadata()
+                    .is_ok_and(|metadata| metadata.dev() == device)
+            });
+            if entry.file_type.is_dir() && below_max_depth && same_device {
+                let ancestors = match self.follow {
+                    Follow::Always => Ancestor::push(job.ancestors.clone(), &entry),
+                    _ => None,
+                };
+                pending.fetch_add(1, Ordering::SeqCst);
+                local.push((
+                    index,
+                    Job {
+                        path: entry.path,
+                        depth: entry.depth,
+                        ignores,
+                        ancestors,
+                        device: job.device,
+                    },
+                ));
+                self.idle.wake_one();
+            }
+        }
+    }
+
+    /// Visit entries as they appear in the directories read so far, until the walk is ended by
+    /// -quit, --max-results or an error writing output. Batched commands are run after each
+    /// burst of changes.
+    #[cfg(target_os = "linux")]
+    fn watch(&self, watcher: &Watcher<(usize, Job)>) -> io::Result<()> {
+        let local = Worker::new_lifo();
+        let pending = AtomicUsize::new(0);
+        self.expr.flush(self.errors);
+        while !self.quit.load(Ordering::Relaxed) {
+            for ((index, job), name) in watcher.wait(self.errors)? {
+                let path = job.path.join(name);
+                match fs::symlink_metadata(&path) {
+                    Ok(metadata) => {
+                        let file_type = metadata.file_type();
+                        self.visit_child(index, &job, path, file_type, &local, &pending);
+                    }
+                    // Temporary files often vanish before they can be visited.
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => self.report(&path, &e),
+                }
+                self.work(&local, &Injector::new(), &[], &pending);
+            }
+            self.expr.flush(self.errors);
+        }
+        Ok(())
+    }
+
+    /// Evaluate the expression for an entry found below the starting path at `index`, and decide
+    /// whether to descend into it. `ignores` holds the ignore rules of its parent directory.
+    fn visit(&self, index: usize, entry: &Entry, ignores: Option<&Ignores>) -> Visit {
+        if let Some(ignores) = ignores {
+            if let Some(reason) = ignores.check(entry) {
+                if self.args.explain_ignored {
+                    eprintln!("{}: {}", entry.path().display(), reason);
+                }
+                return Visit::Skip;
+            }
+        }
+        let ignores = ignores
+            .filter(|_| entry.file_type().is_dir())
+            .map(|ignores| ignores.descend(entry, self.errors));
+
+        if entry.depth() < self.args.min_depth.unwrap_or(0) {
+            return Visit::Descend(ignores);
+        }
+
+        let mut context = Context::new(self.sorted_output.is_some(), self.errors);
+        self.expr.matches(entry, &mut context);
+        if context.acted && !self.count_result() {
+            // Another thread has already found enough results.
+            return Visit::Skip;
+        }
+        if context.quit {
+            self.quit.store(true, Ordering::Relaxed);
+        }
+        if context.delete {
+            self.delete(index, entry, &mut context.output);
+        }
+        if context.collect && entry.file_type().is_file() {
+            match entry.metadata() {
+                Ok(metadata) => {
+                    let candidate = Candidate::new(entry.path().to_path_buf(), metadata);
+                    self.candidates.lock().unwrap().push(candidate);
+                }
+                Err(e) => self.report(entry.path(), &e),
+            }
+        }
+        if let Err(e) = self.output(index, entry, context.output) {
+            self.fail(e);
+        }
+        if let Some(e) = context.error {
+            self.fail(e);
+        }
+
+        if context.prune {
+            Visit::Skip
+        } else {
+            Visit::Descend(ignores)
+        }
+    }
+
+    fn output(&self, index: usize, entry: &Entry, output: Vec<u8>) -> io::Result<()> {
+        if output.is_empty() {
+            return Ok(());
+        }
+        match &self.sorted_output {
+            Some(sorted) => {
+                let key = (index, entry.path().to_path_buf());
+                sorted.lock().unwrap().push((key, output));
+                Ok(())
+            }
+            None => io::stdout().lock().write_all(&output),
+        }
+    }
+
+    /// Remove an entry matched by -delete, or with --dry-run, describe its removal in `output`.
+    /// Directories are set aside to be removed at the end of the walk.
+    fn delete(&self, index: usize, entry: &Entry, output: &mut Vec<u8>) {
+        // As with find, a starting path naming the current directory is left alone, since it can't
+        // be removed by that name.
+        let path = entry.path().as_os_str().as_bytes();
+        if entry.depth() == 0 && (path == b"." || path.ends_with(b"/.")) {
+            return;
+        }
+
+        let root = self.real_roots[index].as_deref();
+        if !root.is_some_and(|root| is_within(entry, root)) {
+            self.errors.report(format_args!(
+                "{}: refusing to remove an entry outside {}",
+                entry.path().display(),
+                self.args.paths[index]
+            ));
+            return;
+        }
+
+        if entry.file_type().is_dir() {
+            let doomed = (entry.depth(), entry.path().to_path_buf());
+            self.doomed_dirs.lock().unwrap().push(doomed);
+        } else if self.args.dry_run {
+            let _ = write_removal(output, entry.path());
+        } else if let Err(e) = remove(entry.path()) {
+            self.errors
+                .report(format_args!("{}: {}", entry.path().display(), e));
+        }
+    }
+
+    /// Count an entry for which an action ran, ending the walk once --max-results have been found.
+    /// Returns false if the entry is surplus to the limit, and must be ignored.
+    fn count_result(&self) -> bool {
+        let count = self.results.fetch_add(1, Ordering::SeqCst) + 1;
+        match self.args.max_results {
+            Some(max) if count >= max => {
+                self.quit.store(true, Ordering::Relaxed);
+                count == max
+            }
+            _ => true,
+        }
+    }
+
+    /// Report an error reading `path` in the same format as walkdir.
+    fn report(&self, path: &Path, e: &io::Error) {
+        self.errors.report(format_args!(
+            "IO error for operation on {}: {}",
+            path.display(),
+            e
+        ));
+    }
+
+    /// Record an error writing output and end the walk.
+    fn fail(&self, e: io::Error) {
+        self.error.lock().unwrap().get_or_insert(e);
+        self.quit.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Take a job from the local queue, or else from the global queue or another worker.
+fn find_job<T>(local: &Worker<T>, injector: &Injector<T>, stealers: &[Stealer<T>]) -> Option<T> {
+    local.pop().or_else(|| {
+        iter::repeat_with(|| {
+            injector
+                .steal_batch_and_pop(local)
+                .or_else(|| stealers.iter().map(Stealer::steal).collect::<Steal<T>>())
+        })
+        .find(|steal| !steal.is_retry())
+        .and_then(Steal::success)
+    })
+}
+
+/// True if `entry` lies within `root`, a starting path with all links resolved. Links in the entry's
+/// parent directories are resolved, but the entry itself may be a link, which removes just the
+/// link.
+fn is_within(entry: &Entry, root: &Path) -> bool {
+    match entry.path().parent() {
+        Some(parent) if entry.depth() > 0 => {
+            fs::canonicalize(parent).is_ok_and(|parent| parent.starts_with(root))
+        }
+        _ => true,
+    }
+}
+
+/// Describe the removal of `path` for --dry-run, writing the path as raw bytes as -print does.
+fn write_removal(out: &mut impl Write, path: &Path) -> io::Result<()> {
+    out.write_all(b"would remove ")?;
+    out.write_all(path.as_os_str().as_bytes())?;
+    out.write_all(b"\n")
+}
+
+/// Remove a file or an empty directory. A link is removed rather than its target.
+fn remove(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
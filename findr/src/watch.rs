@@ -0,0 +1,72 @@
+//! Notification of entries created in or moved into the directories read by the walk, as used by
+//! --watch. Relies on inotify, so only works on Linux.
+//!
+//! Each directory is watched before it is read, so that nothing created in between is missed, at
+//! the cost of occasionally reporting an entry the walk has already visited.
+
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+
+use crate::errors::Errors;
+
+/// Room for a generous number of events, each of which is at most 16 bytes plus a file name.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Watches directories, attaching to each the state the walk needs to visit its new entries.
+pub(crate) struct Watcher<T> {
+    inotify: Mutex<Inotify>,
+    watches: Watches,
+    dirs: Mutex<HashMap<WatchDescriptor, T>>,
+}
+
+impl<T: Clone> Watcher<T> {
+    pub(crate) fn new() -> io::Result<Watcher<T>> {
+        let inotify = Inotify::init()?;
+        Ok(Watcher {
+            watches: inotify.watches(),
+            inotify: Mutex::new(inotify),
+            dirs: Mutex::default(),
+        })
+    }
+
+    /// Watch the directory at `path` for new entries, which will be returned along with `dir`.
+    pub(crate) fn add(&self, path: &Path, dir: T) -> io::Result<()> {
+        let mask = WatchMask::CREATE | WatchMask::MOVED_TO | WatchMask::ONLYDIR;
+        let wd = self.watches.clone().add(path, mask)?;
+        self.dirs.lock().unwrap().insert(wd, dir);
+        Ok(())
+    }
+
+    /// Wait for entries to appear in the watched directories, returning the name of each along
+    /// with the state attached to its directory. Reports any events lost because too many arrived
+    /// at once.
+    pub(crate) fn wait(&self, errors: &Errors) -> io::Result<Vec<(T, OsString)>> {
+        let mut buffer = vec![0; BUFFER_SIZE];
+        let mut inotify = self.inotify.lock().unwrap();
+        let events = loop {
+            match inotify.read_events_blocking(&mut buffer) {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                result => break result?,
+            }
+        };
+
+        let mut dirs = self.dirs.lock().unwrap();
+        let mut created = Vec::new();
+        for event in events {
+            if event.mask.contains(EventMask::Q_OVERFLOW) {
+                errors.report("--watch: too many changes at once; some entries may be missed");
+            } else if event.mask.contains(EventMask::IGNORED) {
+                // The directory has been removed, or is on a filesystem that was unmounted.
+                dirs.remove(&event.wd);
+            } else if let (Some(dir), Some(name)) = (dirs.get(&event.wd), event.name) {
+                created.push((dir.clone(), name.to_os_string()));
+            }
+        }
+        Ok(created)
+    }
+}
@@ -29,10 +29,24 @@ fn skips_bad_dir() -> TestResult {
     let bad = gen_bad_file();
     let expected = format!("{}: .* [(]os error [23][)]", &bad);
     Command::cargo_bin(PRG)?
-        .arg(&bad)
+        .args(["tests/inputs/a", &bad])
         .assert()
-        .success()
-        .stderr(predicate::str::is_match(expected)?);
+        .failure()
+        .stdout(predicate::str::contains("tests/inputs/a/b"))
+        .stderr(predicate::str::is_match(expected)?)
+        .stderr(predicate::str::ends_with("findr: 1 error\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn quiet_errors() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a", &gen_bad_file(), "--quiet-errors"])
+        .assert()
+        .failure()
+        .stdout(predicate::str::contains("tests/inputs/a/b"))
+        .stderr("");
     Ok(())
 }
 
@@ -52,7 +66,7 @@ fn dies_bad_name() -> TestResult {
 fn dies_bad_type() -> TestResult {
     let expected = "invalid value 'x' for '--type [<TYPE>...]'";
     Command::cargo_bin(PRG)?
-        .args(&["--type", "x"])
+        .args(["--type", "x"])
         .assert()
         .failure()
         .stderr(predicate::str::contains(expected));
@@ -61,14 +75,14 @@ fn dies_bad_type() -> TestResult {
 
 // --------------------------------------------------
 #[cfg(windows)]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Owned(format!("{}.windows", expected_file))
     format!("{}.windows", expected_file).into()
 }
 
 // --------------------------------------------------
 #[cfg(not(windows))]
-fn format_file_name(expected_file: &str) -> Cow<str> {
+fn format_file_name(expected_file: &str) -> Cow<'_, str> {
     // Equivalent to: Cow::Borrowed(expected_file)
     expected_file.into()
 }
@@ -274,6 +288,1085 @@ fn type_d_name_a() -> TestResult {
     )
 }
 
+// --------------------------------------------------
+#[test]
+fn expr_name_csv_or_type_d() -> TestResult {
+    run(
+        &["tests/inputs", "-name", "*.csv", "-o", "-type", "d"],
+        "tests/expected/expr_name_csv_or_type_d.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_not_path_a() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "(",
+            "-name",
+            "*.csv",
+            "-or",
+            "-name",
+            "*.mp3",
+            ")",
+            "!",
+            "-path",
+            "*/inputs/a/*",
+        ],
+        "tests/expected/expr_not_path_a.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn glob_iname() -> TestResult {
+    run(
+        &["tests/inputs", "-iname", "[AG].*"],
+        "tests/expected/glob_iname.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn glob_ipath() -> TestResult {
+    run(
+        &["tests/inputs", "-ipath", "*/D/?.TS?"],
+        "tests/expected/glob_ipath.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_whole_path() -> TestResult {
+    run(
+        &["tests/inputs", "-regex", ".*/[a-c]\\.(csv|mp3)"],
+        "tests/expected/regex_whole_path.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn regex_partial_path() -> TestResult {
+    run(
+        &["tests/inputs", "-regex", "inputs/a"],
+        "tests/expected/empty.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn iregex() -> TestResult {
+    run(
+        &["tests/inputs", "-iregex", ".*/A/.*\\.CSV"],
+        "tests/expected/iregex.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_size_2c() -> TestResult {
+    run(
+        &["tests/inputs", "-size", "2c"],
+        "tests/expected/expr_size_2c.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn expr_size_gt_1_not_dir() -> TestResult {
+    run(
+        &["tests/inputs", "-size", "+1c", "-and", "-not", "-type", "d"],
+        "tests/expected/expr_size_gt_1_not_dir.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn expr_mtime() -> TestResult {
+    let dir = std::env::temp_dir().join(format!("findr-{}", gen_bad_file()));
+    fs::create_dir(&dir)?;
+    fs::write(dir.join("new.txt"), "new")?;
+    fs::write(dir.join("old.txt"), "old")?;
+    std::process::Command::new("touch")
+        .args(["-d", "2000-01-01"])
+        .arg(dir.join("old.txt"))
+        .status()?;
+
+    let find = |age: &str| -> Result<String, Box<dyn std::error::Error>> {
+        let out = Command::cargo_bin(PRG)?
+            .arg(&dir)
+            .args(["-type", "f", "-mtime", age])
+            .output()?;
+        Ok(String::from_utf8(out.stdout)?)
+    };
+    let recent = find("-1");
+    let stale = find("+365");
+    fs::remove_dir_all(&dir)?;
+
+    assert_eq!(recent?, format!("{}\n", dir.join("new.txt").display()));
+    assert_eq!(stale?, format!("{}\n", dir.join("old.txt").display()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_depth_1() -> TestResult {
+    run(
+        &["tests/inputs", "--max-depth", "1"],
+        "tests/expected/max_depth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_depth_3() -> TestResult {
+    run(
+        &["tests/inputs", "--min-depth", "3"],
+        "tests/expected/min_depth_3.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn min_depth_2_max_depth_2() -> TestResult {
+    run(
+        &["tests/inputs", "--min-depth", "2", "--max-depth", "2"],
+        "tests/expected/min_depth_2_max_depth_2.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn prune_a_b() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-name",
+            "[ab]",
+            "-prune",
+            "-o",
+            "-name",
+            "*.csv",
+        ],
+        "tests/expected/prune_a_b.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_echo_csv() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-name",
+            "*.csv",
+            "-exec",
+            "echo",
+            "found",
+            "{}",
+            ";",
+        ],
+        "tests/expected/exec_echo_csv.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_false_or_print() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-type",
+            "f",
+            "-exec",
+            "false",
+            ";",
+            "-o",
+            "-print",
+        ],
+        "tests/expected/exec_false_or_print.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_batch() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/d", "-type", "f", "-exec", "echo", "{}", "+"])
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(
+            "^(tests/inputs/d/[^ ]+ ){2}tests/inputs/d/[^ ]+\n$",
+        )?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn exec_batch_failure() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-exec", "false", "{}", "+"])
+        .assert()
+        .failure()
+        .stderr("-exec false: command failed\nfindr: 1 error\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a", "nope", "-exec", "false", "{}", "+"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::ends_with(
+            "-exec false: command failed\nfindr: 2 errors\n",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn print0() -> TestResult {
+    let cmd = Command::cargo_bin(PRG)?
+        .args(["tests/inputs/a", "-type", "f", "-print0"])
+        .assert()
+        .success();
+    let out = cmd.get_output();
+
+    let mut paths: Vec<&[u8]> = out.stdout.split(|&b| b == b'\0').collect();
+    assert_eq!(paths.pop(), Some(&b""[..]), "output must end with NUL");
+    paths.sort();
+
+    let expected: [&[u8]; 3] = [
+        b"tests/inputs/a/a.txt",
+        b"tests/inputs/a/b/b.csv",
+        b"tests/inputs/a/b/c/c.mp3",
+    ];
+    assert_eq!(paths, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn printf() -> TestResult {
+    run(
+        &["tests/inputs/a", "-printf", "%d %y %f|%h|%P|%H|%p\\n"],
+        "tests/expected/printf.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn print_non_utf8_name() -> TestResult {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+
+    let dir = std::env::temp_dir().join(format!("findr-{}", gen_bad_file()));
+    let name = OsStr::from_bytes(b"bad\xffname\nwith newline");
+    fs::create_dir(&dir)?;
+    fs::write(dir.join(name), "")?;
+
+    let out = Command::cargo_bin(PRG)?
+        .arg(&dir)
+        .args(["-type", "f", "-print0"])
+        .output();
+    fs::remove_dir_all(&dir)?;
+
+    let mut expected = dir.join(name).into_os_string().into_vec();
+    expected.push(b'\0');
+    assert_eq!(out?.stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn printf_unreadable_metadata() -> TestResult {
+    let tree = TempTree::new()?;
+    let (dir, name) = tree.add_unstatable()?;
+    Command::cargo_bin(PRG)?
+        .arg(&dir)
+        .args(["-type", "f", "--sort", "-printf", "%s %f\\n"])
+        .assert()
+        .failure()
+        .stdout("0 short\n")
+        .stderr(predicate::str::contains(format!(
+            "{}: File name too long",
+            name
+        )));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn ls_and_json_unreadable_metadata() -> TestResult {
+    let tree = TempTree::new()?;
+    let (dir, name) = tree.add_unstatable()?;
+    let expected_error = format!("{}: File name too long", name);
+
+    let out = Command::cargo_bin(PRG)?
+        .arg(&dir)
+        .args(["-type", "f", "--sort", "-ls"])
+        .output()?;
+    assert!(!out.status.success());
+    let stdout = String::from_utf8(out.stdout)?;
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.ends_with("/short\n"));
+    assert!(String::from_utf8(out.stderr)?.contains(&expected_error));
+
+    let out = Command::cargo_bin(PRG)?
+        .arg(&dir)
+        .args(["-type", "f", "--sort", "--json"])
+        .output()?;
+    assert!(!out.status.success());
+    let stdout = String::from_utf8(out.stdout)?;
+    let lines: Vec<_> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].contains(r#""size":null,"mtime":null,"mode":null"#));
+    assert!(lines[1].contains(r#""size":0"#));
+    assert!(String::from_utf8(out.stderr)?.contains(&expected_error));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[cfg(not(windows))]
+struct TempTree(std::path::PathBuf);
+
+#[cfg(not(windows))]
+impl TempTree {
+    /// Create a directory containing `empty.txt`, `full.txt` with mode 0755, an empty
+    /// subdirectory `empty` and a subdirectory `full` holding `old.txt`, last modified in 2000.
+    fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let root = std::env::temp_dir().join(format!("findr-{}", gen_bad_file()));
+        fs::create_dir_all(root.join("empty"))?;
+        fs::create_dir_all(root.join("full"))?;
+        fs::write(root.join("empty.txt"), "")?;
+        fs::write(root.join("full.txt"), "full")?;
+        fs::set_permissions(root.join("full.txt"), fs::Permissions::from_mode(0o755))?;
+        fs::write(root.join("full/old.txt"), "old")?;
+        std::process::Command::new("touch")
+            .args(["-d", "2000-01-01"])
+            .arg(root.join("full/old.txt"))
+            .status()?;
+        Ok(TempTree(root))
+    }
+
+    /// Create a directory nested deep enough in the tree that, while it can be read, the path of
+    /// the file with a long name that it holds is too long for the file's metadata to be read. It
+    /// also holds a file named `short`. Returns the directory and the long file name.
+    fn add_unstatable(&self) -> Result<(std::path::PathBuf, String), Box<dyn std::error::Error>> {
+        let mut dir = self.0.clone();
+        while dir.as_os_str().len() < 3800 {
+            dir.push("d".repeat(250));
+        }
+        fs::create_dir_all(&dir)?;
+        let name = "f".repeat(250);
+        std::process::Command::new("touch")
+            .current_dir(&dir)
+            .args([&name, "short"])
+            .status()?;
+        Ok((dir, name))
+    }
+
+    /// Run findr over the tree, returning the sorted matches relative to the root.
+    fn find(&self, args: &[&str]) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let out = Command::cargo_bin(PRG)?
+            .arg(&self.0)
+            .args(args)
+            .args(["-printf", "%P\\n"])
+            .output()?;
+        let stdout = String::from_utf8(out.stdout)?;
+        let mut lines: Vec<String> = stdout.lines().map(String::from).collect();
+        lines.sort();
+        Ok(lines)
+    }
+}
+
+#[cfg(not(windows))]
+impl Drop for TempTree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn empty() -> TestResult {
+    let tree = TempTree::new()?;
+    assert_eq!(tree.find(&["-empty"])?, ["empty", "empty.txt"]);
+    assert_eq!(
+        tree.find(&["-type", "f", "!", "-empty"])?,
+        ["full.txt", "full/old.txt"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn perm() -> TestResult {
+    let tree = TempTree::new()?;
+    assert_eq!(tree.find(&["-perm", "755", "-type", "f"])?, ["full.txt"]);
+    assert_eq!(tree.find(&["-perm", "-111", "-type", "f"])?, ["full.txt"]);
+    assert_eq!(tree.find(&["-perm", "/011", "-type", "f"])?, ["full.txt"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn newer_and_times() -> TestResult {
+    let tree = TempTree::new()?;
+    let old = tree.0.join("full/old.txt");
+    let newer = ["-type", "f", "-newer", old.to_str().unwrap()];
+    assert_eq!(tree.find(&newer)?, ["empty.txt", "full.txt"]);
+    assert_eq!(
+        tree.find(&["-type", "f", "-mtime", "+365"])?,
+        ["full/old.txt"]
+    );
+    assert_eq!(
+        tree.find(&["-type", "f", "-atime", "+365"])?,
+        ["full/old.txt"]
+    );
+    // touch can't backdate the inode change time.
+    assert!(tree.find(&["-ctime", "+365"])?.is_empty());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn owner_and_inum() -> TestResult {
+    use std::os::unix::fs::MetadataExt;
+
+    let tree = TempTree::new()?;
+    let metadata = fs::metadata(tree.0.join("full.txt"))?;
+    let (uid, gid, ino) = (
+        metadata.uid().to_string(),
+        metadata.gid().to_string(),
+        metadata.ino().to_string(),
+    );
+    assert_eq!(tree.find(&["-user", &uid, "-group", &gid])?.len(), 6);
+    assert_eq!(tree.find(&["-inum", &ino])?, ["full.txt"]);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn ignore_files() -> TestResult {
+    let tree = TempTree::new()?;
+    let root = &tree.0;
+    fs::write(root.join(".gitignore"), "*.txt\nempty/\n")?;
+    fs::write(root.join(".ignore"), "!full.txt\n")?;
+    fs::write(root.join("full/.gitignore"), "!old.txt\n")?;
+
+    let find = |args: &[&str]| -> Result<_, Box<dyn std::error::Error>> {
+        // Isolate the test from the user's global excludes file.
+        let out = Command::cargo_bin(PRG)?
+            .env("HOME", root)
+            .env("XDG_CONFIG_HOME", root)
+            .arg(root)
+            .args(args)
+            .args(["-printf", "%P\\n"])
+            .output()?;
+        let mut lines: Vec<String> = String::from_utf8(out.stdout)?
+            .lines()
+            .map(String::from)
+            .collect();
+        lines.sort();
+        Ok((lines, String::from_utf8(out.stderr)?))
+    };
+
+    let (ignored, _) = find(&["--ignore", "-type", "f"])?;
+    assert_eq!(
+        ignored,
+        [
+            ".gitignore",
+            ".ignore",
+            "full.txt",
+            "full/.gitignore",
+            "full/old.txt"
+        ]
+    );
+
+    let (parallel, _) = find(&["--ignore", "-type", "f", "--threads", "3"])?;
+    assert_eq!(parallel, ignored);
+
+    let (all, _) = find(&["-type", "f"])?;
+    assert_eq!(all.len(), 6);
+
+    let (_, explained) = find(&["--ignore", "--explain-ignored"])?;
+    let empty_txt = root.join("empty.txt");
+    let gitignore = root.join(".gitignore");
+    assert!(explained.contains(&format!(
+        "{}: ignored by '*.txt' in {}",
+        empty_txt.display(),
+        gitignore.display()
+    )));
+    assert!(explained.contains("ignored by 'empty/'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn ignore_files_in_repository() -> TestResult {
+    // The root stands in for a home directory with a .gitignore of its own, holding a repository.
+    let tree = TempTree::new()?;
+    let root = &tree.0;
+    let repo = root.join("full");
+    fs::write(root.join(".gitignore"), "*\n")?;
+    fs::create_dir_all(repo.join(".git/refs"))?;
+    fs::write(repo.join(".git/HEAD"), "ref: refs/heads/main\n")?;
+    fs::write(repo.join(".gitignore"), "*.log\n")?;
+    fs::create_dir(repo.join("src"))?;
+    fs::write(repo.join("src/main.rs"), "")?;
+    fs::write(repo.join("src/debug.log"), "")?;
+
+    for (start, expected) in [
+        (
+            repo.clone(),
+            vec![".gitignore", "old.txt", "src", "src/main.rs"],
+        ),
+        (repo.join("src"), vec!["main.rs"]),
+    ] {
+        let out = Command::cargo_bin(PRG)?
+            .env("HOME", root)
+            .env("XDG_CONFIG_HOME", root)
+            .arg(&start)
+            .args(["--ignore", "--min-depth", "1", "--sort", "-printf", "%P\\n"])
+            .assert()
+            .success();
+        let stdout = String::from_utf8(out.get_output().stdout.clone())?;
+        assert_eq!(stdout.lines().collect::<Vec<_>>(), expected);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn follow_links() -> TestResult {
+    let tree = TempTree::new()?;
+    let root = &tree.0;
+    std::os::unix::fs::symlink("full", root.join("link"))?;
+    std::os::unix::fs::symlink("nowhere", root.join("broken"))?;
+    std::os::unix::fs::symlink("..", root.join("full/up"))?;
+
+    for threads in ["1", "3"] {
+        let find =
+            |args: &[&str]| tree.find(&[&["-j", threads, "--min-depth", "1"], args].concat());
+        assert_eq!(find(&["-type", "l"])?, ["broken", "full/up", "link"]);
+        assert_eq!(
+            find(&["-xtype", "d"])?,
+            ["empty", "full", "full/up", "link"]
+        );
+        assert_eq!(find(&["-L", "-type", "l"])?, ["broken"]);
+        assert_eq!(find(&["-L", "-xtype", "l"])?, ["broken", "link"]);
+        assert_eq!(find(&["-L", "-path", "*/link/*"])?, ["link/old.txt"]);
+
+        Command::cargo_bin(PRG)?
+            .args(["-L", "-j", threads])
+            .arg(root)
+            .assert()
+            .stderr(predicate::str::contains(format!(
+                "File system loop found: {} points to an ancestor {}",
+                root.join("full/up").display(),
+                root.display()
+            )));
+
+        let link = root.join("link");
+        for (mode, expected) in [("-P", 1), ("-H", 3)] {
+            let out = Command::cargo_bin(PRG)?
+                .args([mode, "-j", threads])
+                .arg(&link)
+                .output()?;
+            assert_eq!(String::from_utf8(out.stdout)?.lines().count(), expected);
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn one_file_system() -> TestResult {
+    use std::os::unix::fs::MetadataExt;
+
+    // /proc is normally a filesystem of its own, mounted below /.
+    if !Path::new("/proc/self").exists() || fs::metadata("/proc")?.dev() == fs::metadata("/")?.dev()
+    {
+        return Ok(());
+    }
+
+    // Prune everything but /proc, so that the walk doesn't read directories the user can't. Entries
+    // in /proc can still vanish while it's read, so only the output is checked.
+    for threads in ["1", "3"] {
+        let args = [
+            "/",
+            "--max-depth",
+            "2",
+            "-path",
+            "/",
+            "-o",
+            "(",
+            "-path",
+            "/proc",
+            "-o",
+            "-path",
+            "/proc/*",
+            ")",
+            "-print",
+            "-o",
+            "-prune",
+            "--quiet-errors",
+            "-j",
+            threads,
+        ];
+        for xdev in ["-xdev", "--one-file-system"] {
+            Command::cargo_bin(PRG)?
+                .args(args)
+                .arg(xdev)
+                .assert()
+                .stdout("/proc\n");
+        }
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .assert()
+            .stdout(predicate::str::contains("/proc/self\n"));
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete() -> TestResult {
+    let tree = TempTree::new()?;
+    let root = &tree.0;
+    let outside = TempTree::new()?;
+    std::os::unix::fs::symlink(&outside.0, root.join("outside"))?;
+
+    let expected = format!(
+        "would remove {0}/full/old.txt\nwould remove {0}/full\n",
+        root.display()
+    );
+    Command::cargo_bin(PRG)?
+        .arg(root)
+        .args(["(", "-name", "full", "-o", "-path", "*/full/*", ")"])
+        .args(["-delete", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(expected);
+    assert!(root.join("full/old.txt").exists());
+
+    Command::cargo_bin(PRG)?
+        .arg(root)
+        .args(["(", "-name", "full", "-o", "-path", "*/full/*", ")"])
+        .arg("-delete")
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        tree.find(&["--min-depth", "1"])?,
+        ["empty", "empty.txt", "full.txt", "outside"]
+    );
+
+    Command::cargo_bin(PRG)?
+        .arg(root)
+        .args(["-L", "-name", "old.txt", "-delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "old.txt: refusing to remove an entry outside",
+        ));
+    assert!(outside.0.join("full/old.txt").exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete_current_dir() -> TestResult {
+    let tree = TempTree::new()?;
+    fs::remove_dir_all(tree.0.join("full"))?;
+
+    Command::cargo_bin(PRG)?
+        .current_dir(&tree.0)
+        .args([".", "-delete", "--dry-run", "--sort"])
+        .assert()
+        .success()
+        .stdout("would remove ./empty.txt\nwould remove ./full.txt\nwould remove ./empty\n");
+
+    Command::cargo_bin(PRG)?
+        .current_dir(&tree.0)
+        .args([".", "-delete"])
+        .assert()
+        .success()
+        .stderr("");
+    assert!(tree.find(&["--min-depth", "1"])?.is_empty());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn delete_dry_run_non_utf8_name() -> TestResult {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let tree = TempTree::new()?;
+    let dir = tree.0.join(OsStr::from_bytes(b"bad\xffdir"));
+    let file = dir.join(OsStr::from_bytes(b"bad\xfefile"));
+    fs::create_dir(&dir)?;
+    fs::write(&file, "")?;
+
+    let mut expected = Vec::new();
+    for path in [&file, &dir] {
+        expected.extend_from_slice(b"would remove ");
+        expected.extend_from_slice(path.as_os_str().as_bytes());
+        expected.push(b'\n');
+    }
+    let out = Command::cargo_bin(PRG)?
+        .arg(&tree.0)
+        .args(["-path", "*/bad*", "-delete", "--dry-run"])
+        .output()?;
+    assert_eq!(out.stdout, expected);
+    assert!(file.exists());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn json_and_ls() -> TestResult {
+    let tree = TempTree::new()?;
+    let full_txt = tree.0.join("full.txt");
+
+    let expected = format!(
+        r#"^\{{"path":"{}","type":"f","size":4,"mtime":\d+,"mode":"755","depth":0\}}\n$"#,
+        full_txt.display()
+    );
+    Command::cargo_bin(PRG)?
+        .arg(&full_txt)
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(expected)?);
+
+    let expected = format!(
+        r"^ *\d+ +\d+ -rwxr-xr-x +1 \S+ +\S+ +4 \w{{3}} [ \d]\d [ \d]\d:\d\d {}\n$",
+        full_txt.display()
+    );
+    Command::cargo_bin(PRG)?
+        .arg(&full_txt)
+        .arg("-ls")
+        .assert()
+        .success()
+        .stdout(predicate::str::is_match(expected)?);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn contains() -> TestResult {
+    let tree = TempTree::new()?;
+    fs::write(tree.0.join("binary.dat"), b"full\0of zeros")?;
+    fs::write(tree.0.join("lines.txt"), "first\nfull stop\n")?;
+
+    assert_eq!(
+        tree.find(&["-contains", "full"])?,
+        ["full.txt", "lines.txt"]
+    );
+    assert_eq!(tree.find(&["-contains", "^full$"])?, ["full.txt"]);
+    assert_eq!(tree.find(&["-contains", "(?i)OLD"])?, ["full/old.txt"]);
+    assert_eq!(
+        tree.find(&[
+            "(",
+            "-contains",
+            "full",
+            "-name",
+            "*.txt",
+            "-o",
+            "-name",
+            "*.dat",
+            ")"
+        ])?,
+        ["binary.dat", "full.txt", "lines.txt"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn special_types() -> TestResult {
+    let tree = TempTree::new()?;
+    std::process::Command::new("mkfifo")
+        .arg(tree.0.join("fifo"))
+        .status()?;
+    let _socket = std::os::unix::net::UnixListener::bind(tree.0.join("socket"))?;
+
+    assert_eq!(tree.find(&["-type", "p"])?, ["fifo"]);
+    assert_eq!(tree.find(&["--type", "s"])?, ["socket"]);
+    assert_eq!(tree.find(&["-type", "p,s"])?, ["fifo", "socket"]);
+    assert_eq!(tree.find(&["-type", "b,c"])?, Vec::<String>::new());
+    assert_eq!(tree.find(&["-name", "fifo"])?, ["fifo"]);
+
+    Command::cargo_bin(PRG)?
+        .args(["/dev/null", "-type", "c", "-printf", "%y\\n"])
+        .assert()
+        .success()
+        .stdout("c\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(windows))]
+fn duplicates() -> TestResult {
+    let tree = TempTree::new()?;
+    let big = "x".repeat(10_000);
+    fs::write(tree.0.join("copy.txt"), "full")?;
+    fs::write(tree.0.join("full/big1"), format!("{}a", big))?;
+    fs::write(tree.0.join("full/big2"), format!("{}b", big))?;
+    fs::write(tree.0.join("full/big3"), format!("{}a", big))?;
+    fs::write(tree.0.join("empty/also-empty.txt"), "")?;
+    fs::hard_link(tree.0.join("full.txt"), tree.0.join("full/link.txt"))?;
+
+    let root = tree.0.to_str().unwrap();
+    let expected =
+        format!("{root}/copy.txt\n{root}/full/link.txt\n\n{root}/full/big1\n{root}/full/big3\n");
+    for threads in ["1", "4"] {
+        Command::cargo_bin(PRG)?
+            .args([root, "--duplicates", "-j", threads])
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+
+    Command::cargo_bin(PRG)?
+        .args([root, "--duplicates", "--json", "-name", "big*"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{{\"size\":10001,\"paths\":[\"{root}/full/big1\",\"{root}/full/big3\"]}}\n"
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(not(target_os = "linux"))]
+fn watch_unsupported() -> TestResult {
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--watch"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--watch is only supported on Linux",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")]
+fn watch() -> TestResult {
+    use std::process::Stdio;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    let tree = TempTree::new()?;
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+        .arg(&tree.0)
+        .args(["--watch", "-name", "*.new", "--max-results", "3"])
+        .args(["-printf", "%P\\n"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // Give the initial walk time to finish, and each new directory time to be watched.
+    sleep(Duration::from_millis(500));
+    fs::write(tree.0.join("full/a.new"), "")?;
+    fs::write(tree.0.join("ignored.txt"), "")?;
+    fs::create_dir(tree.0.join("sub"))?;
+    fs::write(tree.0.join("sub/b.tmp"), "")?;
+    sleep(Duration::from_millis(500));
+    fs::rename(tree.0.join("sub/b.tmp"), tree.0.join("sub/b.new"))?;
+    fs::create_dir(tree.0.join("sub.new"))?;
+
+    let start = Instant::now();
+    while child.try_wait()?.is_none() {
+        if start.elapsed() > Duration::from_secs(10) {
+            child.kill()?;
+            panic!("findr --watch didn't stop after --max-results");
+        }
+        sleep(Duration::from_millis(50));
+    }
+    let out = child.wait_with_output()?;
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stdout)?,
+        "full/a.new\nsub/b.new\nsub.new\n"
+    );
+
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--watch", "-delete"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "-delete can't be used with --watch",
+        ));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_path1() -> TestResult {
+    run(
+        &["tests/inputs", "--threads", "4"],
+        "tests/expected/path1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_max_depth_1() -> TestResult {
+    run(
+        &["tests/inputs", "-j", "4", "--max-depth", "1"],
+        "tests/expected/max_depth_1.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn threads_prune_a_b() -> TestResult {
+    run(
+        &[
+            "tests/inputs",
+            "-j",
+            "4",
+            "-name",
+            "[ab]",
+            "-prune",
+            "-o",
+            "-name",
+            "*.csv",
+        ],
+        "tests/expected/prune_a_b.txt",
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn quit() -> TestResult {
+    let out = Command::cargo_bin(PRG)?
+        .args([
+            "tests/inputs/a",
+            "tests/inputs/d",
+            "-type",
+            "f",
+            "-print",
+            "-quit",
+        ])
+        .output()?;
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout)?.lines().count(), 1);
+
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "-quit"])
+        .assert()
+        .success()
+        .stdout("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_results() -> TestResult {
+    for threads in ["1", "4"] {
+        let out = Command::cargo_bin(PRG)?
+            .args(["tests/inputs", "-j", threads, "--max-results", "2"])
+            .args(["-type", "f"])
+            .output()?;
+        assert!(out.status.success());
+        assert_eq!(String::from_utf8(out.stdout)?.lines().count(), 2);
+    }
+
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--max-results", "1", "-name", "nothing"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr("");
+    Command::cargo_bin(PRG)?
+        .args(["tests/inputs", "--max-results", "0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("must be at least 1"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn max_results_prune() -> TestResult {
+    // The pruned directory isn't a result, so it doesn't use up the limit.
+    for threads in ["1", "4"] {
+        Command::cargo_bin(PRG)?
+            .args(["tests/inputs", "-j", threads, "--max-results", "1"])
+            .args(["-name", "a", "-prune", "-o", "-name", "*.csv", "-print"])
+            .assert()
+            .success()
+            .stdout("tests/inputs/g.csv\n");
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort() -> TestResult {
+    let expected = fs::read_to_string("tests/expected/sort.txt")?;
+    for threads in ["1", "4"] {
+        Command::cargo_bin(PRG)?
+            .args(["tests/inputs/d", "tests/inputs/a", "--sort", "-j", threads])
+            .assert()
+            .success()
+            .stdout(expected.clone());
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_expr() -> TestResult {
+    for (args, expected) in [
+        (&["-name"][..], "missing argument to '-name'"),
+        (&["(", "-type", "f"], "expected ')'"),
+        (&["-type", "f", ")"], "unexpected ')'"),
+        (&["-size", "10q"], "invalid -size unit in '10q'"),
+        (&["-exec", "echo", "{}"], "missing argument to '-exec'"),
+        (
+            &["-exec", "echo", "{}", "{}", "+"],
+            "only one instance of {}",
+        ),
+    ] {
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(expected));
+    }
+    Ok(())
+}
+
 // --------------------------------------------------
 #[test]
 fn path_g() -> TestResult {
@@ -301,7 +1394,7 @@ fn unreadable_dir() -> TestResult {
     let cmd = Command::cargo_bin(PRG)?
         .arg("tests/inputs")
         .assert()
-        .success();
+        .failure();
     fs::remove_dir(dirname)?;
 
     let out = cmd.get_output();